@@ -0,0 +1,109 @@
+// Compares the opcode table lookup with the linear scan over the opcodes that decode used before it, run with cargo bench.
+// The whole decode is timed too to show how much of it the lookup is.
+// There is no benchmark harness on stable so this times the loops itself, the best of a few runs is printed.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use assembler_8086::{decode, lookup_opcode_mnemonic};
+
+// A mix of the instructions from the listings: register and memory movs, arithmetic with immediates, jumps, the stack,
+// the string instructions and the groups that are selected with the REG field.
+const PROGRAM: [u8; 48] = [
+    0x89, 0xd9, 0x8b, 0x41, 0x02, 0xb9, 0x03, 0x00, 0x83, 0xc1, 0x04, 0x81, 0x82, 0xe8, 0x03, 0x1d,
+    0x00, 0x03, 0x18, 0x75, 0xfc, 0x50, 0x5b, 0xf3, 0xa4, 0xf7, 0xe3, 0xfe, 0x04, 0xd1, 0xe0, 0xc6,
+    0x07, 0x22, 0xe8, 0x00, 0x00, 0xc3, 0x26, 0x8a, 0x07, 0xa1, 0xfb, 0x09, 0xff, 0x36, 0x10, 0x00,
+];
+const ITERATIONS: usize = 20_000;
+
+// The bit patterns in the order the old construct_opcodes pushed them, the scan cost depends on where the byte is in the list.
+const LINEAR_OPCODES: [u8; 224] = [
+    0x8b, 0x89, 0x8a, 0x88, 0x38, 0x39, 0x3a, 0x3b, 0x28, 0x29, 0x2a, 0x2b, 0x28, 0x29, 0x2a, 0x2b,
+    0x00, 0x01, 0x02, 0x03, 0x20, 0x21, 0x22, 0x23, 0x08, 0x09, 0x0a, 0x0b, 0x30, 0x31, 0x32, 0x33,
+    0x84, 0x85, 0x8c, 0x8e, 0x10, 0x11, 0x12, 0x13, 0x18, 0x19, 0x1a, 0x1b, 0xc7, 0xc6, 0x80, 0x83,
+    0x81, 0x82, 0x80, 0xf6, 0xf7, 0xb0, 0xb1, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba,
+    0xbb, 0xbc, 0xbd, 0xbe, 0xbf, 0x04, 0x05, 0x3c, 0x3d, 0x2c, 0x2d, 0x24, 0x25, 0x0c, 0x0d, 0x34,
+    0x35, 0xa8, 0xa9, 0x14, 0x15, 0x1c, 0x1d, 0x74, 0x7c, 0x7e, 0x72, 0x76, 0x7a, 0x70, 0x78, 0x75,
+    0x7d, 0x7f, 0x73, 0x77, 0x7b, 0x71, 0x79, 0xe2, 0xe1, 0xe0, 0xe3, 0x50, 0x51, 0x52, 0x53, 0x54,
+    0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f, 0x06, 0x0e, 0x16, 0x1e, 0x07,
+    0x0f, 0x17, 0x1f, 0xfe, 0xff, 0x8f, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49,
+    0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f, 0xd0, 0xd1, 0xd2, 0xd3, 0xa4, 0xa5, 0xa6, 0xa7, 0xaa, 0xab,
+    0xac, 0xad, 0xae, 0xaf, 0x27, 0x2f, 0x37, 0x3f, 0xd4, 0xd5, 0xf8, 0xf9, 0xf5, 0xfc, 0xfd, 0xfa,
+    0xfb, 0xf4, 0x9b, 0x90, 0xe4, 0xe5, 0xe6, 0xe7, 0xec, 0xed, 0xee, 0xef, 0xa0, 0xa1, 0xa2, 0xa3,
+    0x8d, 0xc5, 0xc4, 0x86, 0x87, 0x91, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0xd7, 0x98, 0x99, 0x9e,
+    0x9f, 0x9c, 0x9d, 0xe8, 0x9a, 0xe9, 0xeb, 0xea, 0xc3, 0xc2, 0xcb, 0xca, 0xcd, 0xcc, 0xce, 0xcf,
+];
+
+struct OpCode {
+    bit_pattern: u8,
+    t: usize,
+}
+
+// The old path without the decoding around it, the index into the list stands in for the InstructionType.
+fn construct_opcodes() -> Vec<OpCode> {
+    return LINEAR_OPCODES.iter().enumerate().map(|(t, &bit_pattern)| OpCode{bit_pattern, t}).collect()
+}
+
+fn determine_instruction(op_codes: &Vec<OpCode>, first_byte: u8) -> Option<usize> {
+    for op_code in op_codes {
+        if op_code.bit_pattern == first_byte {
+            return Some(op_code.t);
+        }
+    }
+    return None
+}
+
+// 0xF6 and 0xF7 with the REG field above 001 were told apart from test after the scan.
+fn determine_group_instruction(instruction: usize, first_byte: u8, second_byte: u8) -> usize {
+    if (first_byte == 0b11110110 || first_byte == 0b11110111) && second_byte & 0b00111000 > 0b00001000 {
+        return usize::MAX;
+    }
+    return instruction
+}
+
+fn time<F: FnMut()>(name: &str, per_iteration: usize, mut f: F) {
+    let mut best = Duration::MAX;
+    for _ in 0..5 {
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            f();
+        }
+        best = best.min(start.elapsed());
+    }
+    println!("{:<24} {:>8.1} ns per instruction", name, best.as_nanos() as f64 / (ITERATIONS * per_iteration) as f64);
+}
+
+fn main() {
+    let mut offsets: Vec<usize> = Vec::new();
+    let mut offset = 0;
+    while offset < PROGRAM.len() {
+        // The prefixes were read before the scan, the first byte is the one after them.
+        let instruction = decode(&PROGRAM, offset).unwrap();
+        offsets.push(offset + instruction.prefixes.size);
+        offset += instruction.length;
+    }
+    let instruction_count = offsets.len();
+
+    // The whole decode, the first byte is looked up from the opcode table.
+    time("decode", instruction_count, || {
+        let mut offset = 0;
+        while offset < PROGRAM.len() {
+            offset += black_box(decode(black_box(&PROGRAM), offset).unwrap()).length;
+        }
+    });
+    // Only the first byte lookup, over the same bytes for both. The table lookup is a call into the library
+    // while the scan can be inlined into the bench, so if anything the scan has the advantage.
+    time("opcode table lookup", instruction_count, || {
+        for &offset in offsets.iter() {
+            let (first_byte, second_byte) = (black_box(PROGRAM[offset]), black_box(PROGRAM[offset + 1]));
+            black_box(lookup_opcode_mnemonic(first_byte, second_byte));
+        }
+    });
+    let op_codes = construct_opcodes();
+    time("linear opcode scan", instruction_count, || {
+        for &offset in offsets.iter() {
+            let (first_byte, second_byte) = (black_box(PROGRAM[offset]), black_box(PROGRAM[offset + 1]));
+            black_box(determine_instruction(&op_codes, first_byte).map(|instruction| determine_group_instruction(instruction, first_byte, second_byte)));
+        }
+    });
+}
@@ -13,9 +13,12 @@
 // Lexical analyzer type of phase to get tokens out of the bit patterns.
 
 use crate::bits::Masks::{D_BITS, MOD_BITS};
+use crate::instruction::{Mnemonic, RepeatPrefix};
+use crate::registers::SegmentRegister;
 use crate::bits::MemoryModeEnum::{DirectMemoryOperation, MemoryMode16Bit, MemoryMode8Bit, MemoryModeNoDisplacement, RegisterMode};
 
 // InstructionTable contains all the possible instructions that we are trying to decode.
+#[allow(clippy::upper_case_acronyms)]
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum InstructionType {
     RegisterMemory,
@@ -24,6 +27,12 @@ pub enum InstructionType {
     ImmediateToAccumulatorADD,
     ImmediateToAccumulatorSUB,
     ImmediateToAccumulatorCMP,
+    ImmediateToAccumulatorAND,
+    ImmediateToAccumulatorOR,
+    ImmediateToAccumulatorXOR,
+    ImmediateToAccumulatorTEST,
+    ImmediateToAccumulatorADC,
+    ImmediateToAccumulatorSBB,
     JE_JUMP,
     JL_JUMP,
     JLE_JUMP,
@@ -44,6 +53,72 @@ pub enum InstructionType {
     LOOPZ,
     LOOPNZ,
     JCXZ,
+    PushRegister,
+    PopRegister,
+    PushSegmentRegister,
+    PopSegmentRegister,
+    // The 0xFE, 0xFF and 0x8F opcodes take a single R/M operand, the REG field of the second byte tells the instructions apart.
+    // 0xF6 and 0xF7 are also single operand instructions unless the REG field is 000, then it's test with an immediate.
+    SingleOperandRegisterMemory,
+    IncrementRegister,
+    DecrementRegister,
+    PUSHF,
+    POPF,
+    CALL_NEAR,
+    CALL_FAR,
+    JMP_NEAR,
+    JMP_SHORT,
+    JMP_FAR,
+    RET_NEAR,
+    RET_NEAR_IMMEDIATE,
+    RET_FAR,
+    RET_FAR_IMMEDIATE,
+    // 0xD0 - 0xD3 shift or rotate the R/M operand by 1 or by cl, the REG field tells which shift or rotate it is.
+    ShiftOrRotate,
+    // movs, cmps, stos, lods and scas, the lowest bit is the W bit.
+    StringOperation,
+    DAA,
+    DAS,
+    AAA,
+    AAS,
+    // aam and aad have the base in the second byte, it's 10 unless the assembler was told otherwise.
+    AAM,
+    AAD,
+    CBW,
+    CWD,
+    SAHF,
+    LAHF,
+    CLC,
+    STC,
+    CMC,
+    CLD,
+    STD,
+    CLI,
+    STI,
+    HLT,
+    WAIT,
+    NOP,
+    // lea, lds and les have the destination register in the REG field and the memory operand in the R/M field.
+    LEA,
+    LDS,
+    LES,
+    ExchangeRegisterMemory,
+    // 0x91 - 0x97 exchange ax with the register in the first byte, 0x90 would be xchg ax, ax which is nop.
+    ExchangeRegisterWithAccumulator,
+    XLAT,
+    // The short forms of mov al/ax, [address] and mov [address], al/ax. The address comes right after the opcode.
+    MemoryToAccumulator,
+    AccumulatorToMemory,
+    // in and out have the port either in the second byte or in dx.
+    InFixedPort,
+    OutFixedPort,
+    InVariablePort,
+    OutVariablePort,
+    // int has the interrupt number in the second byte, int3 is the one byte version for interrupt 3.
+    INT,
+    INT3,
+    INTO,
+    IRET,
 }
 
 pub fn instruction_is_conditional_jump(instruction: InstructionType) -> bool {
@@ -72,6 +147,57 @@ pub fn instruction_is_conditional_jump(instruction: InstructionType) -> bool {
     }
 }
 
+// The loop instructions are conditional jumps that also decrement cx.
+pub fn instruction_is_loop(instruction: InstructionType) -> bool {
+    match instruction {
+        InstructionType::LOOP => true,
+        InstructionType::LOOPZ => true,
+        InstructionType::LOOPNZ => true,
+        _ => false,
+    }
+}
+
+pub fn instruction_is_stack_operation(instruction: InstructionType) -> bool {
+    match instruction {
+        InstructionType::PushRegister => true,
+        InstructionType::PopRegister => true,
+        InstructionType::PushSegmentRegister => true,
+        InstructionType::PopSegmentRegister => true,
+        InstructionType::PUSHF => true,
+        InstructionType::POPF => true,
+        _ => false,
+    }
+}
+
+// The unconditional control transfers, the indirect calls and jumps are a part of SingleOperandRegisterMemory so they are not here.
+pub fn instruction_is_call_jump_or_return(instruction: InstructionType) -> bool {
+    match instruction {
+        InstructionType::CALL_NEAR => true,
+        InstructionType::CALL_FAR => true,
+        InstructionType::JMP_NEAR => true,
+        InstructionType::JMP_SHORT => true,
+        InstructionType::JMP_FAR => true,
+        InstructionType::RET_NEAR => true,
+        InstructionType::RET_NEAR_IMMEDIATE => true,
+        InstructionType::RET_FAR => true,
+        InstructionType::RET_FAR_IMMEDIATE => true,
+        _ => false,
+    }
+}
+
+// These instructions have the register in the lowest 3 bits of the first byte instead of a REG field in the second byte.
+pub fn instruction_has_register_in_first_byte(instruction: InstructionType) -> bool {
+    match instruction {
+        InstructionType::ImmediateToRegisterMOV => true,
+        InstructionType::PushRegister => true,
+        InstructionType::PopRegister => true,
+        InstructionType::IncrementRegister => true,
+        InstructionType::DecrementRegister => true,
+        InstructionType::ExchangeRegisterWithAccumulator => true,
+        _ => false,
+    }
+}
+
 pub fn instruction_uses_memory(memory_mode: MemoryModeEnum) -> bool {
     match memory_mode {
         MemoryModeNoDisplacement | MemoryMode8Bit | MemoryMode16Bit | DirectMemoryOperation => true,
@@ -79,34 +205,54 @@ pub fn instruction_uses_memory(memory_mode: MemoryModeEnum) -> bool {
     }
 }
 
-pub fn instruction_is_immediate_to_register(instruction: InstructionType) -> bool {
+// The MOV to/from segment register instructions are decoded as RegisterMemory, the REG field selects es, cs, ss or ds.
+pub fn instruction_is_segment_register_mov(first_byte: u8) -> bool {
+    return first_byte == 0b10001100 || first_byte == 0b10001110
+}
+
+// The segment override prefixes make the memory operand of the next instruction use another segment register than the default one.
+pub fn get_segment_override_prefix(byte: u8) -> Option<SegmentRegister> {
+    match byte {
+        0b00100110 => Some(SegmentRegister::ES),
+        0b00101110 => Some(SegmentRegister::CS),
+        0b00110110 => Some(SegmentRegister::SS),
+        0b00111110 => Some(SegmentRegister::DS),
+        _ => None,
+    }
+}
+
+// The lock prefix makes the next instruction have exclusive access to the memory, it does not change what the instruction does.
+pub fn instruction_is_lock_prefix(byte: u8) -> bool {
+    return byte == 0b11110000
+}
+
+// The repeat prefixes repeat the next string instruction cx times. With cmps and scas F3 is repe (repeat while equal)
+// and F2 is repne (repeat while not equal), with the other string instructions they both just repeat.
+pub fn get_repeat_prefix(byte: u8) -> Option<RepeatPrefix> {
+    match byte {
+        0b11110011 => Some(RepeatPrefix::Rep),
+        0b11110010 => Some(RepeatPrefix::Repne),
+        _ => None,
+    }
+}
+
+// The immediate to accumulator instructions don't have a second byte with the MOD, REG and R/M fields,
+// the register is always ax or al depending on the W bit.
+pub fn instruction_is_immediate_to_accumulator(instruction: InstructionType) -> bool {
     match instruction {
-        InstructionType::ImmediateToRegisterMemory => true,
-        InstructionType::ImmediateToRegisterMOV => true,
         InstructionType::ImmediateToAccumulatorADD => true,
         InstructionType::ImmediateToAccumulatorSUB => true,
         InstructionType::ImmediateToAccumulatorCMP => true,
+        InstructionType::ImmediateToAccumulatorAND => true,
+        InstructionType::ImmediateToAccumulatorOR => true,
+        InstructionType::ImmediateToAccumulatorXOR => true,
+        InstructionType::ImmediateToAccumulatorTEST => true,
+        InstructionType::ImmediateToAccumulatorADC => true,
+        InstructionType::ImmediateToAccumulatorSBB => true,
         _ => false,
     }
 }
-// OpCode exists because we want each bit to know which InstructionType it belongs to.
-// this is because we will be iterating and matching the bit patterns and if we match we want to
-// immediately know which instruction type it is.
-
-// Contains all the possible bit patterns for the first bytes of MOV, CMP, SUB and ADD register/to/from/memory operations.
-const REGISTER_MEMORY_OPERATION: [u8; 20] = [
-    0b10001011, 0b10001001, 0b10001010, 0b10001000, 0b00111000, 0b00111001, 0b00111010, 0b00111011,
-    0b00101000, 0b00101001, 0b00101010, 0b00101011, 0b00101000, 0b00101001, 0b00101010, 0b00101011,
-    0b00000000, 0b00000001, 0b00000010, 0b00000011
-];
-
-// Contains all the possible ImmediateToRegisterOrMemory patterns from the first byte for MOV, ADD, CMP, SUB.
-// the bit pattern is the same for ADD, SUB, CMP but different for MOV.
-// MOV = 110011{1/0}
-// ADD, SUB, CMP = 100000{0/1,0/1}
-const IMMEDIATE_TO_REGISTER_OR_MEMORY_ID: [u8; 7] = [
-    0b11000111, 0b11000110, 0b10000000, 0b10000011, 0b10000001, 0b10000010, 0b10000000
-];
+// The bit patterns of the first byte, construct_opcode_table puts them into OPCODE_TABLE.
 
 // The mov immediate to register has a bit pattern of {1011{0/1,0/1,0/1,0/1}}
 const IMMEDIATE_TO_REGISTER_MOV_ID: [u8; 16] = [
@@ -114,6 +260,42 @@ const IMMEDIATE_TO_REGISTER_MOV_ID: [u8; 16] = [
     0b10111000, 0b10111001, 0b10111010, 0b10111011, 0b10111100, 0b10111101, 0b10111110, 0b10111111,
 ];
 
+// push is 01010reg and pop is 01011reg.
+const PUSH_REGISTER_ID: [u8; 8] = [
+    0b01010000, 0b01010001, 0b01010010, 0b01010011, 0b01010100, 0b01010101, 0b01010110, 0b01010111,
+];
+
+const POP_REGISTER_ID: [u8; 8] = [
+    0b01011000, 0b01011001, 0b01011010, 0b01011011, 0b01011100, 0b01011101, 0b01011110, 0b01011111,
+];
+
+// The segment register push is 000sr110 and pop is 000sr111, sr being the segment register.
+const PUSH_SEGMENT_REGISTER_ID: [u8; 4] = [
+    0b00000110, 0b00001110, 0b00010110, 0b00011110,
+];
+
+const POP_SEGMENT_REGISTER_ID: [u8; 4] = [
+    0b00000111, 0b00001111, 0b00010111, 0b00011111,
+];
+
+// inc is 01000reg and dec is 01001reg.
+const INCREMENT_REGISTER_ID: [u8; 8] = [
+    0b01000000, 0b01000001, 0b01000010, 0b01000011, 0b01000100, 0b01000101, 0b01000110, 0b01000111,
+];
+
+const DECREMENT_REGISTER_ID: [u8; 8] = [
+    0b01001000, 0b01001001, 0b01001010, 0b01001011, 0b01001100, 0b01001101, 0b01001110, 0b01001111,
+];
+
+const EXCHANGE_REGISTER_WITH_ACCUMULATOR_ID: [u8; 7] = [
+    0b10010001, 0b10010010, 0b10010011, 0b10010100, 0b10010101, 0b10010110, 0b10010111,
+];
+
+// The V bit (second lowest bit) tells if the count is 1 or cl, the W bit is the lowest bit like usual.
+const SHIFT_OR_ROTATE_ID: [u8; 4] = [
+    0b11010000, 0b11010001, 0b11010010, 0b11010011
+];
+
 const IMMEDIATE_TO_ACCUMULATOR_ADD_ID: [u8; 2] = [
     0b00000100, 0b00000101
 ];
@@ -126,108 +308,244 @@ const IMMEDIATE_TO_ACCUMULATOR_CMP_ID: [u8; 2] = [
     0b00111100, 0b00111101
 ];
 
+const IMMEDIATE_TO_ACCUMULATOR_AND_ID: [u8; 2] = [
+    0b00100100, 0b00100101
+];
+
+const IMMEDIATE_TO_ACCUMULATOR_OR_ID: [u8; 2] = [
+    0b00001100, 0b00001101
+];
+
+const IMMEDIATE_TO_ACCUMULATOR_XOR_ID: [u8; 2] = [
+    0b00110100, 0b00110101
+];
+
+const IMMEDIATE_TO_ACCUMULATOR_TEST_ID: [u8; 2] = [
+    0b10101000, 0b10101001
+];
+
+const IMMEDIATE_TO_ACCUMULATOR_ADC_ID: [u8; 2] = [
+    0b00010100, 0b00010101
+];
 
-pub struct OpCode {
-    bit_pattern: u8,
-    t: InstructionType,
+const IMMEDIATE_TO_ACCUMULATOR_SBB_ID: [u8; 2] = [
+    0b00011100, 0b00011101
+];
+
+
+// What the first byte of an instruction decodes to. The InstructionType is the operand template, it tells how the operands
+// and the length are decoded. The Mnemonic is the operation that execute dispatches on.
+// The group opcodes need the REG field of the ModRM byte to tell the instruction.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum OpCodeEntry {
+    Unknown,
+    Instruction(InstructionType, Mnemonic),
+    // Indexed with the REG field, None is a combination the 8086 does not have.
+    Group(&'static [Option<(InstructionType, Mnemonic)>; 8]),
 }
 
-// Constructs the OpCode structs into an array of OpCodes from all the const Instruction ID arrays,
-// we do this instead of embedding the structs into the array directly because it looks dirty as hell.
-
-// this is done because we want to determine the operation by looping over all the bit_patterns and on a match
-// we will look at the InstructionType.
-pub fn construct_opcodes() -> Vec<OpCode> {
-    let elements_size: usize = REGISTER_MEMORY_OPERATION.len()
-        + IMMEDIATE_TO_REGISTER_OR_MEMORY_ID.len()
-        + IMMEDIATE_TO_REGISTER_MOV_ID.len()
-        + 15; // 15 = jumps
-    let mut op_codes: Vec<OpCode> = Vec::with_capacity(elements_size);
-
-    for reg_memory in REGISTER_MEMORY_OPERATION {
-        let op_code = OpCode {
-            bit_pattern: reg_memory,
-            t: InstructionType::RegisterMemory,
-        };
-        op_codes.push(op_code)
-    }
+const IMMEDIATE_TO_REGISTER_MEMORY_GROUP: [Option<(InstructionType, Mnemonic)>; 8] = [
+    Some((InstructionType::ImmediateToRegisterMemory, Mnemonic::Add)),
+    Some((InstructionType::ImmediateToRegisterMemory, Mnemonic::Or)),
+    Some((InstructionType::ImmediateToRegisterMemory, Mnemonic::Adc)),
+    Some((InstructionType::ImmediateToRegisterMemory, Mnemonic::Sbb)),
+    Some((InstructionType::ImmediateToRegisterMemory, Mnemonic::And)),
+    Some((InstructionType::ImmediateToRegisterMemory, Mnemonic::Sub)),
+    Some((InstructionType::ImmediateToRegisterMemory, Mnemonic::Xor)),
+    Some((InstructionType::ImmediateToRegisterMemory, Mnemonic::Cmp)),
+];
 
-    for imm_to_reg_or_memory in IMMEDIATE_TO_REGISTER_OR_MEMORY_ID {
-        let op_code = OpCode {
-            bit_pattern: imm_to_reg_or_memory,
-            t: InstructionType::ImmediateToRegisterMemory,
-        };
-        op_codes.push(op_code)
-    }
+const MOV_IMMEDIATE_TO_REGISTER_MEMORY_GROUP: [Option<(InstructionType, Mnemonic)>; 8] = [
+    Some((InstructionType::ImmediateToRegisterMemory, Mnemonic::Mov)), None, None, None, None, None, None, None,
+];
 
-    for imm_to_reg_mov in IMMEDIATE_TO_REGISTER_MOV_ID {
-        let op_code = OpCode {
-            bit_pattern: imm_to_reg_mov,
-            t: InstructionType::ImmediateToRegisterMOV,
-        };
-        op_codes.push(op_code)
-    }
+// 0xF6 and 0xF7, test is the only one with an immediate. 001 is not documented but the 8086 does the same thing as with 000.
+const TEST_OR_SINGLE_OPERAND_GROUP: [Option<(InstructionType, Mnemonic)>; 8] = [
+    Some((InstructionType::ImmediateToRegisterMemory, Mnemonic::Test)),
+    Some((InstructionType::ImmediateToRegisterMemory, Mnemonic::Test)),
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Not)),
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Neg)),
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Mul)),
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Imul)),
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Div)),
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Idiv)),
+];
 
-    for imm in IMMEDIATE_TO_ACCUMULATOR_ADD_ID {
-        let op_code = OpCode {
-            bit_pattern: imm,
-            t: InstructionType::ImmediateToAccumulatorADD,
-        };
-        op_codes.push(op_code)
-    }
+const INCREMENT_DECREMENT_BYTE_GROUP: [Option<(InstructionType, Mnemonic)>; 8] = [
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Inc)),
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Dec)),
+    None, None, None, None, None, None,
+];
 
-    for imm in IMMEDIATE_TO_ACCUMULATOR_CMP_ID {
-        let op_code = OpCode {
-            bit_pattern: imm,
-            t: InstructionType::ImmediateToAccumulatorCMP,
-        };
-        op_codes.push(op_code)
-    }
+// 0xFF, 011 and 101 are the far versions of call and jmp.
+const INCREMENT_DECREMENT_WORD_GROUP: [Option<(InstructionType, Mnemonic)>; 8] = [
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Inc)),
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Dec)),
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Call)),
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Call)),
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Jmp)),
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Jmp)),
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Push)),
+    None,
+];
 
-    for imm in IMMEDIATE_TO_ACCUMULATOR_SUB_ID {
-        let op_code = OpCode {
-            bit_pattern: imm,
-            t: InstructionType::ImmediateToAccumulatorSUB,
-        };
-        op_codes.push(op_code)
+const POP_REGISTER_MEMORY_GROUP: [Option<(InstructionType, Mnemonic)>; 8] = [
+    Some((InstructionType::SingleOperandRegisterMemory, Mnemonic::Pop)), None, None, None, None, None, None, None,
+];
+
+// 110 is not documented but the 8086 does the same thing as with 100.
+const SHIFT_OR_ROTATE_GROUP: [Option<(InstructionType, Mnemonic)>; 8] = [
+    Some((InstructionType::ShiftOrRotate, Mnemonic::Rol)),
+    Some((InstructionType::ShiftOrRotate, Mnemonic::Ror)),
+    Some((InstructionType::ShiftOrRotate, Mnemonic::Rcl)),
+    Some((InstructionType::ShiftOrRotate, Mnemonic::Rcr)),
+    Some((InstructionType::ShiftOrRotate, Mnemonic::Shl)),
+    Some((InstructionType::ShiftOrRotate, Mnemonic::Shr)),
+    Some((InstructionType::ShiftOrRotate, Mnemonic::Shl)),
+    Some((InstructionType::ShiftOrRotate, Mnemonic::Sar)),
+];
+
+const fn set_opcodes(table: &mut [OpCodeEntry; 256], bit_patterns: &[u8], entry: OpCodeEntry) {
+    let mut i = 0;
+    while i < bit_patterns.len() {
+        table[bit_patterns[i] as usize] = entry;
+        i += 1;
     }
-    op_codes.push(OpCode{bit_pattern: 0b01110100, t: InstructionType::JE_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01111100, t: InstructionType::JL_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01111110, t: InstructionType::JLE_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01110010, t: InstructionType::JB_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01110110, t: InstructionType::JBE_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01111010, t: InstructionType::JP_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01110000, t: InstructionType::JO_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01111000, t: InstructionType::JS_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01110101, t: InstructionType::JNE_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01111101, t: InstructionType::JNL_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01111111, t: InstructionType::JNLE_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01110011, t: InstructionType::JNB_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01110111, t: InstructionType::JNBE_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01111011, t: InstructionType::JNP_JUMP});
-    op_codes.push(OpCode{bit_pattern: 0b01110001, t: InstructionType::JNO_JUMP});
-    // Loop stuff
-    op_codes.push(OpCode{bit_pattern: 0b01111001, t: InstructionType::JNS});
-    op_codes.push(OpCode{bit_pattern: 0b11100010, t: InstructionType::LOOP});
-    op_codes.push(OpCode{bit_pattern: 0b11100001, t: InstructionType::LOOPZ});
-    op_codes.push(OpCode{bit_pattern: 0b11100000, t: InstructionType::LOOPNZ});
-    op_codes.push(OpCode{bit_pattern: 0b11100011, t: InstructionType::JCXZ});
-
-    op_codes
 }
-// We need to call this function because the different instructions are handled
-// in different ways.
-pub fn determine_instruction(op_codes: &Vec<OpCode>, first_byte: u8) -> InstructionType {
-    for op_code in op_codes {
-        if op_code.bit_pattern == first_byte {
-            return op_code.t.clone();
-        }
-    }
 
-    panic!("unsupported operation, first_byte: {:08b}", first_byte);
+// Every instruction indexed with the first byte, so finding the instruction is a single lookup.
+// The prefixes are Unknown, they are read before the table is looked at.
+pub const OPCODE_TABLE: [OpCodeEntry; 256] = construct_opcode_table();
+
+const fn construct_opcode_table() -> [OpCodeEntry; 256] {
+    use InstructionType::*;
+    use Mnemonic::*;
+    use OpCodeEntry::{Group, Instruction};
+
+    let mut table = [OpCodeEntry::Unknown; 256];
+    set_opcodes(&mut table, &[0b00000000, 0b00000001, 0b00000010, 0b00000011], Instruction(RegisterMemory, Add));
+    set_opcodes(&mut table, &[0b00001000, 0b00001001, 0b00001010, 0b00001011], Instruction(RegisterMemory, Or));
+    set_opcodes(&mut table, &[0b00010000, 0b00010001, 0b00010010, 0b00010011], Instruction(RegisterMemory, Adc));
+    set_opcodes(&mut table, &[0b00011000, 0b00011001, 0b00011010, 0b00011011], Instruction(RegisterMemory, Sbb));
+    set_opcodes(&mut table, &[0b00100000, 0b00100001, 0b00100010, 0b00100011], Instruction(RegisterMemory, And));
+    set_opcodes(&mut table, &[0b00101000, 0b00101001, 0b00101010, 0b00101011], Instruction(RegisterMemory, Sub));
+    set_opcodes(&mut table, &[0b00110000, 0b00110001, 0b00110010, 0b00110011], Instruction(RegisterMemory, Xor));
+    set_opcodes(&mut table, &[0b00111000, 0b00111001, 0b00111010, 0b00111011], Instruction(RegisterMemory, Cmp));
+    set_opcodes(&mut table, &[0b10001000, 0b10001001, 0b10001010, 0b10001011], Instruction(RegisterMemory, Mov));
+    // test only has the 1000010w form, the D bit is never set for it.
+    set_opcodes(&mut table, &[0b10000100, 0b10000101], Instruction(RegisterMemory, Test));
+    // The mov to/from segment register is 100011d0, the REG field holds the segment register instead of a general purpose register.
+    set_opcodes(&mut table, &[0b10001100, 0b10001110], Instruction(RegisterMemory, Mov));
+
+    // The immediate forms of the arithmetic and logical operations are 100000sw, the REG field tells them apart.
+    set_opcodes(&mut table, &[0b10000000, 0b10000001, 0b10000010, 0b10000011], Group(&IMMEDIATE_TO_REGISTER_MEMORY_GROUP));
+    set_opcodes(&mut table, &[0b11000110, 0b11000111], Group(&MOV_IMMEDIATE_TO_REGISTER_MEMORY_GROUP));
+    set_opcodes(&mut table, &[0b11110110, 0b11110111], Group(&TEST_OR_SINGLE_OPERAND_GROUP));
+    set_opcodes(&mut table, &[0b11111110], Group(&INCREMENT_DECREMENT_BYTE_GROUP));
+    set_opcodes(&mut table, &[0b11111111], Group(&INCREMENT_DECREMENT_WORD_GROUP));
+    set_opcodes(&mut table, &[0b10001111], Group(&POP_REGISTER_MEMORY_GROUP));
+    set_opcodes(&mut table, &SHIFT_OR_ROTATE_ID, Group(&SHIFT_OR_ROTATE_GROUP));
+
+    set_opcodes(&mut table, &IMMEDIATE_TO_REGISTER_MOV_ID, Instruction(ImmediateToRegisterMOV, Mov));
+    set_opcodes(&mut table, &IMMEDIATE_TO_ACCUMULATOR_ADD_ID, Instruction(ImmediateToAccumulatorADD, Add));
+    set_opcodes(&mut table, &IMMEDIATE_TO_ACCUMULATOR_OR_ID, Instruction(ImmediateToAccumulatorOR, Or));
+    set_opcodes(&mut table, &IMMEDIATE_TO_ACCUMULATOR_ADC_ID, Instruction(ImmediateToAccumulatorADC, Adc));
+    set_opcodes(&mut table, &IMMEDIATE_TO_ACCUMULATOR_SBB_ID, Instruction(ImmediateToAccumulatorSBB, Sbb));
+    set_opcodes(&mut table, &IMMEDIATE_TO_ACCUMULATOR_AND_ID, Instruction(ImmediateToAccumulatorAND, And));
+    set_opcodes(&mut table, &IMMEDIATE_TO_ACCUMULATOR_SUB_ID, Instruction(ImmediateToAccumulatorSUB, Sub));
+    set_opcodes(&mut table, &IMMEDIATE_TO_ACCUMULATOR_XOR_ID, Instruction(ImmediateToAccumulatorXOR, Xor));
+    set_opcodes(&mut table, &IMMEDIATE_TO_ACCUMULATOR_CMP_ID, Instruction(ImmediateToAccumulatorCMP, Cmp));
+    set_opcodes(&mut table, &IMMEDIATE_TO_ACCUMULATOR_TEST_ID, Instruction(ImmediateToAccumulatorTEST, Test));
+
+    set_opcodes(&mut table, &[0b01110000], Instruction(JO_JUMP, Jo));
+    set_opcodes(&mut table, &[0b01110001], Instruction(JNO_JUMP, Jno));
+    set_opcodes(&mut table, &[0b01110010], Instruction(JB_JUMP, Jb));
+    set_opcodes(&mut table, &[0b01110011], Instruction(JNB_JUMP, Jnb));
+    set_opcodes(&mut table, &[0b01110100], Instruction(JE_JUMP, Je));
+    set_opcodes(&mut table, &[0b01110101], Instruction(JNE_JUMP, Jnz));
+    set_opcodes(&mut table, &[0b01110110], Instruction(JBE_JUMP, Jbe));
+    set_opcodes(&mut table, &[0b01110111], Instruction(JNBE_JUMP, Ja));
+    set_opcodes(&mut table, &[0b01111000], Instruction(JS_JUMP, Js));
+    set_opcodes(&mut table, &[0b01111001], Instruction(JNS, Jns));
+    set_opcodes(&mut table, &[0b01111010], Instruction(JP_JUMP, Jp));
+    set_opcodes(&mut table, &[0b01111011], Instruction(JNP_JUMP, Jnp));
+    set_opcodes(&mut table, &[0b01111100], Instruction(JL_JUMP, Jl));
+    set_opcodes(&mut table, &[0b01111101], Instruction(JNL_JUMP, Jnl));
+    set_opcodes(&mut table, &[0b01111110], Instruction(JLE_JUMP, Jle));
+    set_opcodes(&mut table, &[0b01111111], Instruction(JNLE_JUMP, Jg));
+    set_opcodes(&mut table, &[0b11100000], Instruction(LOOPNZ, Loopnz));
+    set_opcodes(&mut table, &[0b11100001], Instruction(LOOPZ, Loopz));
+    set_opcodes(&mut table, &[0b11100010], Instruction(LOOP, Loop));
+    set_opcodes(&mut table, &[0b11100011], Instruction(JCXZ, Jcxz));
+
+    set_opcodes(&mut table, &PUSH_REGISTER_ID, Instruction(PushRegister, Push));
+    set_opcodes(&mut table, &POP_REGISTER_ID, Instruction(PopRegister, Pop));
+    set_opcodes(&mut table, &PUSH_SEGMENT_REGISTER_ID, Instruction(PushSegmentRegister, Push));
+    set_opcodes(&mut table, &POP_SEGMENT_REGISTER_ID, Instruction(PopSegmentRegister, Pop));
+    set_opcodes(&mut table, &INCREMENT_REGISTER_ID, Instruction(IncrementRegister, Inc));
+    set_opcodes(&mut table, &DECREMENT_REGISTER_ID, Instruction(DecrementRegister, Dec));
+    set_opcodes(&mut table, &[0b10011100], Instruction(PUSHF, Pushf));
+    set_opcodes(&mut table, &[0b10011101], Instruction(POPF, Popf));
+
+    // 0xA8 and 0xA9 in the middle of these are test with the accumulator.
+    set_opcodes(&mut table, &[0b10100100, 0b10100101], Instruction(StringOperation, Movs));
+    set_opcodes(&mut table, &[0b10100110, 0b10100111], Instruction(StringOperation, Cmps));
+    set_opcodes(&mut table, &[0b10101010, 0b10101011], Instruction(StringOperation, Stos));
+    set_opcodes(&mut table, &[0b10101100, 0b10101101], Instruction(StringOperation, Lods));
+    set_opcodes(&mut table, &[0b10101110, 0b10101111], Instruction(StringOperation, Scas));
+
+    set_opcodes(&mut table, &[0b00100111], Instruction(DAA, Daa));
+    set_opcodes(&mut table, &[0b00101111], Instruction(DAS, Das));
+    set_opcodes(&mut table, &[0b00110111], Instruction(AAA, Aaa));
+    set_opcodes(&mut table, &[0b00111111], Instruction(AAS, Aas));
+    set_opcodes(&mut table, &[0b11010100], Instruction(AAM, Aam));
+    set_opcodes(&mut table, &[0b11010101], Instruction(AAD, Aad));
+    set_opcodes(&mut table, &[0b10011000], Instruction(CBW, Cbw));
+    set_opcodes(&mut table, &[0b10011001], Instruction(CWD, Cwd));
+    set_opcodes(&mut table, &[0b10011110], Instruction(SAHF, Sahf));
+    set_opcodes(&mut table, &[0b10011111], Instruction(LAHF, Lahf));
+
+    // The processor control instructions.
+    set_opcodes(&mut table, &[0b11111000], Instruction(CLC, Clc));
+    set_opcodes(&mut table, &[0b11111001], Instruction(STC, Stc));
+    set_opcodes(&mut table, &[0b11110101], Instruction(CMC, Cmc));
+    set_opcodes(&mut table, &[0b11111100], Instruction(CLD, Cld));
+    set_opcodes(&mut table, &[0b11111101], Instruction(STD, Std));
+    set_opcodes(&mut table, &[0b11111010], Instruction(CLI, Cli));
+    set_opcodes(&mut table, &[0b11111011], Instruction(STI, Sti));
+    set_opcodes(&mut table, &[0b11110100], Instruction(HLT, Hlt));
+    set_opcodes(&mut table, &[0b10011011], Instruction(WAIT, Wait));
+    set_opcodes(&mut table, &[0b10010000], Instruction(NOP, Nop));
+
+    set_opcodes(&mut table, &[0b11100100, 0b11100101], Instruction(InFixedPort, In));
+    set_opcodes(&mut table, &[0b11100110, 0b11100111], Instruction(OutFixedPort, Out));
+    set_opcodes(&mut table, &[0b11101100, 0b11101101], Instruction(InVariablePort, In));
+    set_opcodes(&mut table, &[0b11101110, 0b11101111], Instruction(OutVariablePort, Out));
+    set_opcodes(&mut table, &[0b10100000, 0b10100001], Instruction(MemoryToAccumulator, Mov));
+    set_opcodes(&mut table, &[0b10100010, 0b10100011], Instruction(AccumulatorToMemory, Mov));
+    set_opcodes(&mut table, &[0b10001101], Instruction(LEA, Lea));
+    set_opcodes(&mut table, &[0b11000101], Instruction(LDS, Lds));
+    set_opcodes(&mut table, &[0b11000100], Instruction(LES, Les));
+    set_opcodes(&mut table, &[0b10000110, 0b10000111], Instruction(ExchangeRegisterMemory, Xchg));
+    set_opcodes(&mut table, &EXCHANGE_REGISTER_WITH_ACCUMULATOR_ID, Instruction(ExchangeRegisterWithAccumulator, Xchg));
+    set_opcodes(&mut table, &[0b11010111], Instruction(XLAT, Xlat));
+
+    set_opcodes(&mut table, &[0b11101000], Instruction(CALL_NEAR, Call));
+    set_opcodes(&mut table, &[0b10011010], Instruction(CALL_FAR, Call));
+    set_opcodes(&mut table, &[0b11101001], Instruction(JMP_NEAR, Jmp));
+    set_opcodes(&mut table, &[0b11101011], Instruction(JMP_SHORT, Jmp));
+    set_opcodes(&mut table, &[0b11101010], Instruction(JMP_FAR, Jmp));
+    set_opcodes(&mut table, &[0b11000011], Instruction(RET_NEAR, Ret));
+    set_opcodes(&mut table, &[0b11000010], Instruction(RET_NEAR_IMMEDIATE, Ret));
+    set_opcodes(&mut table, &[0b11001011], Instruction(RET_FAR, Retf));
+    set_opcodes(&mut table, &[0b11001010], Instruction(RET_FAR_IMMEDIATE, Retf));
+    set_opcodes(&mut table, &[0b11001101], Instruction(INT, Int));
+    set_opcodes(&mut table, &[0b11001100], Instruction(INT3, Int3));
+    set_opcodes(&mut table, &[0b11001110], Instruction(INTO, Into));
+    set_opcodes(&mut table, &[0b11001111], Instruction(IRET, Iret));
+    return table
 }
 
-
 // MemoryMode is determined by the MOD field in the second byte.
 // 00 = Memory Mode, no displacement
 // 01 = Memory Mode, 8 bit displacement
@@ -278,79 +596,41 @@ pub fn determine_memory_mode(second_byte: u8) -> MemoryModeEnum {
         }
         0b_01000000 => MemoryMode8Bit,
         0b_10000000 => MemoryMode16Bit,
-        0b_11000000 => RegisterMode,
-        _ => panic!("Invalid second_byte bit pattern, could not determine memory mode: {}", second_byte),
+        // 0b_11000000, the MOD field only has 2 bits.
+        _ => RegisterMode,
     }
 }
 
-pub fn determine_instruction_byte_size(inst: InstructionType, is_word_size: bool, memory_mode: MemoryModeEnum, mnemonic: &'static str, s_bit_set: bool) -> usize {
+// The amount of displacement bytes that come after the second byte.
+pub fn get_displacement_byte_size(memory_mode: MemoryModeEnum) -> usize {
+    match memory_mode {
+        MemoryMode8Bit => 1,
+        MemoryMode16Bit | DirectMemoryOperation => 2,
+        MemoryModeNoDisplacement | RegisterMode => 0,
+    }
+}
+
+pub fn determine_instruction_byte_size(inst: InstructionType, is_word_size: bool, memory_mode: MemoryModeEnum, mnemonic: Mnemonic, s_bit_set: bool) -> usize {
     match inst {
         InstructionType::RegisterMemory => {
-            if memory_mode == MemoryModeNoDisplacement {
-                return 2;
-            } else if memory_mode == MemoryMode8Bit {
-                return 3;
-            } else if memory_mode == MemoryMode16Bit {
-                return 4;
-            } else if memory_mode == RegisterMode {
-                return 2;
-            } else if memory_mode == DirectMemoryOperation {
-                return 4;
-            } else {
-                panic!("Unknown memory_mode operation. We did not expect to get here.\nmemory_mode: {:?}, inst: {:?}, is_word_size: {}", memory_mode, inst, is_word_size);
-            }
+            return 2 + get_displacement_byte_size(memory_mode)
         }
         InstructionType::ImmediateToRegisterMemory => {
-            if mnemonic == "mov" {
-                if memory_mode == MemoryMode8Bit {
-                    if is_word_size {
-                        return 5;
-                    } else {
-                        return 4;
-                    }
-                }
-                if memory_mode == MemoryMode16Bit || memory_mode == DirectMemoryOperation {
-                    if is_word_size {
-                        return 6;
-                    } else {
-                        return 5;
-                    }
-                } else {
-                    if is_word_size {
-                        return 4;
-                    } else {
-                        return 3;
-                    }
-                }
-            } else if mnemonic == "add" || mnemonic == "sub" || mnemonic == "cmp" {
-                // add is 01 sw for 16-bit
-                // this means that s bit has to be set to 0 if w is 1 for it to be 6 bytes wide.
-
-                if memory_mode == MemoryMode8Bit {
-                    if is_word_size && !s_bit_set {
-                        return 5;
-                    } else {
-                        return 4;
-                    }
-                }
-                if memory_mode == MemoryMode16Bit || memory_mode == DirectMemoryOperation {
-                    if is_word_size && !s_bit_set {
-                        return 6;
-                    } else {
-                        return 5;
-                    }
-                } else {
-                    if is_word_size && !s_bit_set {
-                        return 4;
-                    } else {
-                        return 3;
-                    }
-              }
-            } else {
-                panic!("Unknown mnemonic, we could not handle it. instruction type: {:?}, mnemonic: {}", inst, mnemonic)
-            }
+            // The immediate comes after the displacement. mov and test don't care about the s bit, the size of their
+            // immediate only depends on the W bit. With the rest the s bit set means a byte that gets sign extended.
+            let immediate_size = if is_word_size && (mnemonic == Mnemonic::Mov || mnemonic == Mnemonic::Test || !s_bit_set) { 2 } else { 1 };
+            return 2 + get_displacement_byte_size(memory_mode) + immediate_size
         }
-        InstructionType::ImmediateToRegisterMOV | InstructionType::ImmediateToAccumulatorSUB | InstructionType::ImmediateToAccumulatorADD  | InstructionType::ImmediateToAccumulatorCMP => {
+        InstructionType::ImmediateToRegisterMOV
+        | InstructionType::ImmediateToAccumulatorSUB
+        | InstructionType::ImmediateToAccumulatorADD
+        | InstructionType::ImmediateToAccumulatorCMP
+        | InstructionType::ImmediateToAccumulatorAND
+        | InstructionType::ImmediateToAccumulatorOR
+        | InstructionType::ImmediateToAccumulatorXOR
+        | InstructionType::ImmediateToAccumulatorTEST
+        | InstructionType::ImmediateToAccumulatorADC
+        | InstructionType::ImmediateToAccumulatorSBB => {
             if is_word_size {
                 return 3;
             } else {
@@ -379,5 +659,80 @@ pub fn determine_instruction_byte_size(inst: InstructionType, is_word_size: bool
         | InstructionType::JCXZ => {
             return 2
         }
+        InstructionType::PushRegister
+        | InstructionType::PopRegister
+        | InstructionType::PushSegmentRegister
+        | InstructionType::PopSegmentRegister
+        | InstructionType::PUSHF
+        | InstructionType::POPF
+        | InstructionType::IncrementRegister
+        | InstructionType::DecrementRegister
+        | InstructionType::StringOperation
+        | InstructionType::DAA
+        | InstructionType::DAS
+        | InstructionType::AAA
+        | InstructionType::AAS
+        | InstructionType::CBW
+        | InstructionType::CWD
+        | InstructionType::SAHF
+        | InstructionType::LAHF
+        | InstructionType::CLC
+        | InstructionType::STC
+        | InstructionType::CMC
+        | InstructionType::CLD
+        | InstructionType::STD
+        | InstructionType::CLI
+        | InstructionType::STI
+        | InstructionType::HLT
+        | InstructionType::WAIT
+        | InstructionType::NOP
+        | InstructionType::ExchangeRegisterWithAccumulator
+        | InstructionType::XLAT => {
+            return 1
+        }
+        InstructionType::MemoryToAccumulator | InstructionType::AccumulatorToMemory => {
+            return 3
+        }
+        InstructionType::InFixedPort | InstructionType::OutFixedPort => {
+            return 2
+        }
+        InstructionType::InVariablePort | InstructionType::OutVariablePort => {
+            return 1
+        }
+        InstructionType::LEA
+        | InstructionType::LDS
+        | InstructionType::LES
+        | InstructionType::ExchangeRegisterMemory => {
+            return 2 + get_displacement_byte_size(memory_mode)
+        }
+        InstructionType::AAM | InstructionType::AAD => {
+            return 2
+        }
+        InstructionType::SingleOperandRegisterMemory | InstructionType::ShiftOrRotate => {
+            return 2 + get_displacement_byte_size(memory_mode)
+        }
+        InstructionType::RET_NEAR | InstructionType::RET_FAR => {
+            return 1
+        }
+        InstructionType::JMP_SHORT => {
+            return 2
+        }
+        InstructionType::INT => {
+            return 2
+        }
+        InstructionType::INT3 | InstructionType::INTO | InstructionType::IRET => {
+            return 1
+        }
+        // The near call and jump have a 16-bit displacement, the returns have a 16-bit amount of bytes to pop.
+        InstructionType::CALL_NEAR
+        | InstructionType::JMP_NEAR
+        | InstructionType::RET_NEAR_IMMEDIATE
+        | InstructionType::RET_FAR_IMMEDIATE => {
+            return 3
+        }
+        // The far call and jump have the 16-bit offset followed by the 16-bit segment.
+        InstructionType::CALL_FAR | InstructionType::JMP_FAR => {
+            return 5
+        }
     }
 }
@@ -0,0 +1,88 @@
+use crate::flag_registers::{construct_flag_registers, get_all_currently_set_flags, FlagRegister};
+use crate::io_bus::IoBus;
+use crate::flag_registers::number_is_signed;
+use crate::memory::{construct_memory, initialize_memory_value, memory_struct, read_memory_value};
+use crate::registers::{construct_registers, get_register_state, set_register_value, update_original_register_value, Register, RegisterState, Value, ValueEnum};
+
+/// The state of the simulated 8086, the decoded instructions are executed against this.
+/// The I/O bus is borrowed so the caller can attach its own devices and look at them after the simulation.
+pub struct Cpu<'a> {
+    pub(crate) registers: Vec<RegisterState>,
+    pub(crate) flag_registers: FlagRegister,
+    pub(crate) memory: Vec<memory_struct>,
+    pub(crate) io_bus: &'a mut dyn IoBus,
+    /// The offset of the next instruction to decode, the binary is handled as if it was loaded at the start of cs.
+    pub instruction_pointer: usize,
+}
+
+impl Cpu<'_> {
+    /// The current value of the register, it's uninitialized if nothing has written to the register yet.
+    pub fn register_value(&self, register: Register) -> Value {
+        return get_register_state(register, &self.registers).updated_value
+    }
+
+    /// Every register with its current value, in the order they are encoded in the instructions.
+    pub fn register_values(&self) -> Vec<(Register, Value)> {
+        return self.registers.iter().map(|register| (register.register, register.updated_value)).collect()
+    }
+
+    /// The names of the flags that are set, in the order they are in the FLAGS register.
+    pub fn flags(&self) -> Vec<&'static str> {
+        return get_all_currently_set_flags(&self.flag_registers)
+    }
+
+    /// Sets the register before the simulation, only the low byte of the value is used with the byte registers.
+    /// The value does not show up as a change in the trace of the next instruction.
+    pub fn set_register_value(&mut self, register: Register, value: u16) {
+        let value = ValueEnum::from_usize(value as usize, register.is_word_size());
+        set_register_value(register, value, &mut self.registers);
+        update_original_register_value(register, value, &mut self.registers);
+    }
+
+    /// Reads the byte or the little endian word at segment:offset, the offset wraps around inside of the segment.
+    /// The value is uninitialized if nothing has written to the memory yet.
+    pub fn read_memory(&self, segment: u16, offset: u16, is_word_size: bool) -> Value {
+        return memory_value(read_memory_value(&self.memory, segment as usize, offset as usize, is_word_size))
+    }
+
+    /// Writes the byte (only the low byte of the value is used) or the little endian word to segment:offset.
+    /// The value does not show up as a change in the trace of the next instruction.
+    pub fn write_memory(&mut self, segment: u16, offset: u16, value: u16, is_word_size: bool) {
+        initialize_memory_value(&mut self.memory, segment as usize, offset as usize, ValueEnum::from_usize(value as usize, is_word_size));
+    }
+
+    /// Like read_memory but with a 20-bit physical address, the addresses past 1 MiB wrap around to the start.
+    pub fn read_physical_memory(&self, address: usize, is_word_size: bool) -> Value {
+        return memory_value(read_memory_value(&self.memory, address >> 4, address & 0xF, is_word_size))
+    }
+
+    /// Like write_memory but with a 20-bit physical address, the addresses past 1 MiB wrap around to the start.
+    pub fn write_physical_memory(&mut self, address: usize, value: u16, is_word_size: bool) {
+        initialize_memory_value(&mut self.memory, address >> 4, address & 0xF, ValueEnum::from_usize(value as usize, is_word_size));
+    }
+
+    /// Copies the program to the start of the segment and points cs:ip at it, so the program can read its own data from memory.
+    /// The instructions are still decoded from the bytes given to decode, the offsets in the binary are the same as the offsets in cs.
+    pub fn load_program(&mut self, segment: u16, binary_contents: &[u8]) {
+        for (i, byte) in binary_contents.iter().enumerate() {
+            self.write_physical_memory(((segment as usize) << 4) + i, *byte as u16, false);
+        }
+        self.set_register_value(Register::CS, segment);
+        self.instruction_pointer = 0;
+    }
+}
+
+fn memory_value(value: ValueEnum) -> Value {
+    return Value{value, is_signed: number_is_signed(value)}
+}
+
+/// A CPU with the registers, the flags and the memory cleared and the instruction pointer at 0.
+pub fn construct_cpu(io_bus: &mut dyn IoBus) -> Cpu<'_> {
+    return Cpu{
+        registers: construct_registers(),
+        flag_registers: construct_flag_registers(),
+        memory: construct_memory(),
+        io_bus,
+        instruction_pointer: 0,
+    }
+}
@@ -0,0 +1,724 @@
+use crate::bits::*;
+
+use crate::memory::get_displacement;
+use crate::bits::combine_bytes;
+use std::fmt;
+use crate::bits::InstructionType::{ImmediateToRegisterMemory, ImmediateToRegisterMOV, RegisterMemory, SingleOperandRegisterMemory, PushRegister, PopRegister, PushSegmentRegister, PopSegmentRegister, IncrementRegister, DecrementRegister, CALL_NEAR, CALL_FAR, JMP_NEAR, JMP_SHORT, JMP_FAR, RET_NEAR_IMMEDIATE, RET_FAR, RET_FAR_IMMEDIATE, ShiftOrRotate, AAM, AAD, LEA, LDS, LES, ExchangeRegisterMemory, ExchangeRegisterWithAccumulator, MemoryToAccumulator, AccumulatorToMemory, InFixedPort, OutFixedPort, InVariablePort, OutVariablePort, INT};
+use crate::bits::Masks::{D_BITS, IMMEDIATE_TO_REG_MOV_W_BIT};
+
+use crate::flag_registers::number_is_signed;
+use crate::bits::MemoryModeEnum::{DirectMemoryOperation, MemoryMode16Bit, MemoryMode8Bit, RegisterMode};
+use crate::registers::{Register, SegmentRegister, Value, ValueEnum};
+use crate::instruction::{EffectiveAddress, Instruction, Mnemonic, Operand, OperandWidth, InstructionPrefixes};
+
+// W bit determines the size between 8 and 16-bits, the w bit is at different places depending on the instruction.
+// This function does not work with the immediate to registers because they use the s bit also, we have to take into consideration
+// that bit separatelyemoveOccurrence
+fn is_word_size(first_byte: u8, inst_type: InstructionType) -> bool {
+    return if inst_type == ImmediateToRegisterMOV {
+        first_byte & IMMEDIATE_TO_REG_MOV_W_BIT as u8 != 0
+    } else if instruction_is_segment_register_mov(first_byte) {
+        // The segment registers are always 16-bits, the bit where W would be is 0.
+        true
+    } else if inst_type == LEA || inst_type == LDS || inst_type == LES || inst_type == ExchangeRegisterWithAccumulator {
+        // These always work on words, the lowest bit of les is 0 but it's not a W bit.
+        true
+    } else {
+        first_byte & Masks::W_BIT as u8 != 0
+    }
+}
+
+const WORD_REGISTERS: [Register; 8] = [Register::AX, Register::CX, Register::DX, Register::BX, Register::SP, Register::BP, Register::SI, Register::DI];
+const BYTE_REGISTERS: [Register; 8] = [Register::AL, Register::CL, Register::DL, Register::BL, Register::AH, Register::CH, Register::DH, Register::BH];
+
+// Returns the register of the REG field (get_reg) or the R/M field, the memory operands are decoded by decode_effective_address.
+// Every 3-bit field is a valid register so this can't fail.
+fn get_register(get_reg: bool, inst: InstructionType, first_byte: u8, second_byte: u8, is_word_size: bool) -> Register {
+    if instruction_is_immediate_to_accumulator(inst) {
+        if is_word_size {
+            return Register::AX
+        } else {
+            return Register::AL
+        }
+    }
+
+    if get_reg && instruction_is_segment_register_mov(first_byte) {
+        return get_segment_register(second_byte >> 3).into();
+    }
+    if inst == PushSegmentRegister || inst == PopSegmentRegister {
+        return get_segment_register(first_byte >> 3).into();
+    }
+
+    let register_bits = if instruction_has_register_in_first_byte(inst) {
+        first_byte & IMMEDIATE_TO_MOV_REG_BITS as u8
+    } else if get_reg {
+        (second_byte & Masks::REG_BITS as u8) >> 3
+    } else {
+        second_byte & Masks::RM_BITS as u8
+    };
+    return if is_word_size {
+        WORD_REGISTERS[register_bits as usize]
+    } else {
+        BYTE_REGISTERS[register_bits as usize]
+    }
+}
+
+// The segment registers are encoded with 2 bits, the bits are in a different place depending on the instruction
+// so the caller shifts them to the lowest bits.
+fn get_segment_register(segment_register_bits: u8) -> SegmentRegister {
+    return [SegmentRegister::ES, SegmentRegister::CS, SegmentRegister::SS, SegmentRegister::DS][(segment_register_bits & 0b11) as usize];
+}
+
+// Reads the prefixes starting from the instruction pointer, if there are multiple prefixes of the same kind the last one wins like on the real chip.
+fn get_instruction_prefixes(binary_contents: &[u8], instruction_pointer: usize) -> InstructionPrefixes {
+    let mut prefixes = InstructionPrefixes{segment_override: None, repeat: None, lock: false, size: 0};
+    loop {
+        // The binary can end in the middle of the prefixes, decode notices that there is no opcode after them.
+        let Some(&byte) = binary_contents.get(instruction_pointer + prefixes.size) else {
+            return prefixes
+        };
+        if let Some(segment_register) = get_segment_override_prefix(byte) {
+            prefixes.segment_override = Some(segment_register);
+        } else if let Some(repeat) = get_repeat_prefix(byte) {
+            prefixes.repeat = Some(repeat);
+        } else if instruction_is_lock_prefix(byte) {
+            prefixes.lock = true;
+        } else {
+            return prefixes
+        }
+        prefixes.size += 1;
+    }
+}
+
+fn get_immediate_from_rm_register(instruction: InstructionType, is_word_size: bool, instruction_pointer: usize, binary_contents: &[u8]) -> Value {
+            // This case is actually the complete opposite from the previous one.
+            // The immediate to register MOV instruction actually does not have the R/M register
+            // but has the REG register it used to move immediate values to.
+            if instruction == ImmediateToRegisterMOV {
+                // and the R/M Register actually is not used at all with the MOV immediate instruction.
+
+                // With the immediate to register mov instruction, the immediate is stored in the second (and third byte if word sized).
+                let second_byte = binary_contents[instruction_pointer + 1];
+                if is_word_size {
+                    let third_byte = binary_contents[instruction_pointer + 2];
+                    let combined = combine_bytes(third_byte, second_byte);
+                    let value = ValueEnum::WordSize(combined);
+                    return Value {
+                        value,
+                        is_signed: number_is_signed(value),
+                    };
+                } else {
+                    let value = ValueEnum::ByteSize(second_byte);
+                    return Value {
+                        value,
+                        is_signed: number_is_signed(value),
+                    };
+            }
+    }
+    panic!("we thought rm register contained an immediate when it did not.")
+}
+
+fn get_immediate_from_reg_register(mnemonic: Mnemonic, instruction: InstructionType, is_s_bit_set: bool, is_word_size: bool, memory_mode: MemoryModeEnum, instruction_pointer: usize, binary_contents: &[u8]) -> Value {
+    if instruction == ImmediateToRegisterMemory {
+        // The immediate comes right after the displacement bytes, if the instruction has any.
+        let immediate_index = instruction_pointer + 2 + get_displacement_byte_size(memory_mode);
+        if !is_word_size {
+            let value = ValueEnum::ByteSize(binary_contents[immediate_index]);
+            return Value{
+                value,
+                is_signed: number_is_signed(value),
+            };
+        } else { // is_word_size
+            // MOV and TEST don't care about the s_bit. CMP, SUB, ADD, AND, OR and XOR do.
+            // if w=1 and s=0 and mnemonic is one of those, it's an 16-bit immediate.
+            match (mnemonic, is_s_bit_set) {
+                (Mnemonic::Mov, _) | (Mnemonic::Test, _) | (Mnemonic::Cmp, false) | (Mnemonic::Add, false) | (Mnemonic::Adc, false) | (Mnemonic::Sub, false) | (Mnemonic::Sbb, false) | (Mnemonic::And, false) | (Mnemonic::Or, false) | (Mnemonic::Xor, false) => {
+                    let combined = combine_bytes(binary_contents[immediate_index + 1], binary_contents[immediate_index]);
+                    let value = ValueEnum::WordSize(combined);
+                    return Value{
+                        value,
+                        is_signed: number_is_signed(value),
+                    };
+                },
+                (Mnemonic::Cmp, true) | (Mnemonic::Add, true) | (Mnemonic::Adc, true) | (Mnemonic::Sub, true) | (Mnemonic::Sbb, true) | (Mnemonic::And, true) | (Mnemonic::Or, true) | (Mnemonic::Xor, true) => {
+                    // With the s bit set the immediate is a single byte that gets sign extended into 16-bits.
+                    let sign_extended = binary_contents[immediate_index] as i8 as i16 as u16;
+                    let value = ValueEnum::WordSize(sign_extended);
+                    return Value{
+                        value,
+                        is_signed: number_is_signed(value),
+                    };
+                }
+                _ => panic!("Unknown (mnemonic, s_bit_is_set): ({})", mnemonic)
+            }
+        }
+    } else if instruction_is_immediate_to_accumulator(instruction) {
+        let second_byte = binary_contents[instruction_pointer + 1];
+        if is_word_size {
+            let third_byte = binary_contents[instruction_pointer + 2];
+            let combined = combine_bytes(third_byte, second_byte);
+            let value = ValueEnum::WordSize(combined);
+            return Value{
+                value,
+                is_signed: number_is_signed(value),
+            };
+
+        } else {
+            let value = ValueEnum::ByteSize(second_byte);
+            return Value{
+                value,
+                is_signed: number_is_signed(value),
+            };
+        }
+    }
+    panic!("We thought that the reg register contained an immediate when it did not.")
+}
+
+/// The reason the bytes at an offset could not be decoded, the offset is where the instruction (with its prefixes) starts.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DecodeError {
+    /// The first byte after the prefixes is not an 8086 instruction.
+    UnknownOpcode {
+        /// Where the instruction starts.
+        offset: usize,
+        /// The byte after the prefixes.
+        opcode: u8,
+    },
+    /// The binary ends before the instruction does.
+    TruncatedInstruction {
+        /// Where the instruction starts.
+        offset: usize,
+        /// The amount of bytes the instruction needs.
+        length: usize,
+        /// The amount of bytes left in the binary.
+        available: usize,
+    },
+    /// The ModRM byte selects something the opcode does not have, the REG field 111 with 0xFF or a register operand with lea for example.
+    InvalidModRM {
+        /// Where the instruction starts.
+        offset: usize,
+        /// The byte after the prefixes.
+        opcode: u8,
+        /// The ModRM byte.
+        mod_rm: u8,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            DecodeError::UnknownOpcode{offset, opcode} => write!(f, "unknown opcode 0x{:02x} at offset {}", opcode, offset),
+            DecodeError::TruncatedInstruction{offset, length, available} => write!(f, "the instruction at offset {} needs {} bytes but only {} are left", offset, length, available),
+            DecodeError::InvalidModRM{offset, opcode, mod_rm} => write!(f, "invalid ModRM byte 0x{:02x} for opcode 0x{:02x} at offset {}", mod_rm, opcode, offset),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+// Returns the operand template and the operation of the first byte, the group opcodes need the REG field of the second byte too.
+// None is an opcode the 8086 does not have or a REG field that is not one of the instructions of the group,
+// 0xFE with anything other than inc and dec for example.
+fn lookup_opcode(first_byte: u8, second_byte: u8) -> Option<(InstructionType, Mnemonic)> {
+    return match OPCODE_TABLE[first_byte as usize] {
+        OpCodeEntry::Instruction(instruction, mnemonic) => Some((instruction, mnemonic)),
+        OpCodeEntry::Group(group) => group[((second_byte & Masks::REG_BITS as u8) >> 3) as usize],
+        OpCodeEntry::Unknown => None,
+    }
+}
+
+// Only public for benches/decode.rs, it times the opcode lookup without the rest of decode.
+#[doc(hidden)]
+pub fn lookup_opcode_mnemonic(first_byte: u8, second_byte: u8) -> Option<Mnemonic> {
+    return lookup_opcode(first_byte, second_byte).map(|(_, mnemonic)| mnemonic)
+}
+
+/// Decodes the instruction that starts at the offset, the prefixes are a part of the instruction. Only the bytes are looked at,
+/// the registers and the memory are not needed for this so disassembling never touches the state of the simulated CPU.
+///
+/// Returns a [`DecodeError`] when the bytes are not a valid instruction or the binary ends in the middle of one.
+pub fn decode(binary_contents: &[u8], offset: usize) -> Result<Instruction, DecodeError> {
+    let prefixes = get_instruction_prefixes(binary_contents, offset);
+    let available = binary_contents.len().saturating_sub(offset);
+    let ip = offset + prefixes.size;
+    let Some(&first_byte) = binary_contents.get(ip) else {
+        return Err(DecodeError::TruncatedInstruction{offset, length: prefixes.size + 1, available});
+    };
+    // The single byte instructions don't have a second byte, they can be the last byte of the binary.
+    // The instructions that need it are truncated, that's noticed when we know their length.
+    let second_byte = binary_contents.get(ip + 1).copied().unwrap_or(0);
+    let invalid_mod_rm = DecodeError::InvalidModRM{offset, opcode: first_byte, mod_rm: second_byte};
+    let Some((instruction, mnemonic)) = lookup_opcode(first_byte, second_byte) else {
+        return Err(if OPCODE_TABLE[first_byte as usize] == OpCodeEntry::Unknown { DecodeError::UnknownOpcode{offset, opcode: first_byte} } else { invalid_mod_rm });
+    };
+    // The stack operations and the ones with the register in the first byte always work on words, the lowest bit is not a W bit with them.
+    let is_word_size = is_word_size(first_byte, instruction)
+        || instruction_is_stack_operation(instruction)
+        || instruction_is_call_jump_or_return(instruction)
+        || (instruction_has_register_in_first_byte(instruction) && instruction != ImmediateToRegisterMOV);
+    let memory_mode = if instruction_is_immediate_to_accumulator(instruction) { RegisterMode } else { determine_memory_mode(second_byte) };
+    let is_s_bit_set = first_byte & S_BIT_M as u8 == 0b00000010;
+    let length = prefixes.size + determine_instruction_byte_size(instruction, is_word_size, memory_mode, mnemonic, is_s_bit_set);
+    if length > available {
+        return Err(DecodeError::TruncatedInstruction{offset, length, available});
+    }
+    let reg_is_dest = first_byte & D_BITS as u8 != 0;
+    let far = matches!(instruction, CALL_FAR | JMP_FAR | RET_FAR | RET_FAR_IMMEDIATE)
+        || (instruction == SingleOperandRegisterMemory && (mnemonic == Mnemonic::Call || mnemonic == Mnemonic::Jmp) && (second_byte & Masks::REG_BITS as u8 == 0b00_011_000 || second_byte & Masks::REG_BITS as u8 == 0b00_101_000));
+    // These load an address or a far pointer from memory, a register can't hold either.
+    if memory_mode == RegisterMode && (matches!(instruction, LEA | LDS | LES) || (far && instruction == SingleOperandRegisterMemory)) {
+        return Err(invalid_mod_rm);
+    }
+    // There are only four segment registers, the REG field 1xx is not one of them.
+    if instruction_is_segment_register_mov(first_byte) && second_byte & 0b00_100_000 != 0 {
+        return Err(invalid_mod_rm);
+    }
+
+    let immediate_word = |index: usize| combine_bytes(binary_contents[ip + index + 1], binary_contents[ip + index]);
+    let accumulator = Operand::Register(if is_word_size { Register::AX } else { Register::AL });
+    let reg_operand = || Operand::Register(get_register(true, instruction, first_byte, second_byte, is_word_size));
+    let rm_operand = || {
+        if memory_mode == RegisterMode {
+            Operand::Register(get_register(false, instruction, first_byte, second_byte, is_word_size))
+        } else {
+            Operand::Memory(decode_effective_address(binary_contents, ip, memory_mode, prefixes.segment_override))
+        }
+    };
+    let direct_address = || Operand::Memory(EffectiveAddress{base: None, index: None, displacement: Some(immediate_word(1) as i16), segment: prefixes.segment_override});
+
+    let operands = match instruction {
+        RegisterMemory if reg_is_dest => vec![reg_operand(), rm_operand()],
+        RegisterMemory => vec![rm_operand(), reg_operand()],
+        LEA | LDS | LES | ExchangeRegisterMemory => vec![reg_operand(), rm_operand()],
+        ImmediateToRegisterMemory => vec![rm_operand(), Operand::Immediate(get_immediate_from_reg_register(mnemonic, instruction, is_s_bit_set, is_word_size, memory_mode, ip, binary_contents).value)],
+        ImmediateToRegisterMOV => vec![reg_operand(), Operand::Immediate(get_immediate_from_rm_register(instruction, is_word_size, ip, binary_contents).value)],
+        _ if instruction_is_immediate_to_accumulator(instruction) => vec![accumulator, Operand::Immediate(get_immediate_from_reg_register(mnemonic, instruction, is_s_bit_set, is_word_size, memory_mode, ip, binary_contents).value)],
+        _ if instruction_is_conditional_jump(instruction) => vec![Operand::RelativeTarget(second_byte as i8 as i16)],
+        SingleOperandRegisterMemory => vec![rm_operand()],
+        ShiftOrRotate if first_byte & 0b00000010 != 0 => vec![rm_operand(), Operand::Register(Register::CL)],
+        ShiftOrRotate => vec![rm_operand(), Operand::Immediate(ValueEnum::ByteSize(1))],
+        PushRegister | PopRegister | IncrementRegister | DecrementRegister | PushSegmentRegister | PopSegmentRegister => vec![reg_operand()],
+        ExchangeRegisterWithAccumulator => vec![Operand::Register(Register::AX), Operand::Register(get_register(false, instruction, first_byte, second_byte, true))],
+        CALL_NEAR | JMP_NEAR => vec![Operand::RelativeTarget(immediate_word(1) as i16)],
+        JMP_SHORT => vec![Operand::RelativeTarget(second_byte as i8 as i16)],
+        CALL_FAR | JMP_FAR => vec![Operand::FarPointer{segment: immediate_word(3), offset: immediate_word(1)}],
+        RET_NEAR_IMMEDIATE | RET_FAR_IMMEDIATE => vec![Operand::Immediate(ValueEnum::WordSize(immediate_word(1)))],
+        AAM | AAD | INT => vec![Operand::Immediate(ValueEnum::ByteSize(second_byte))],
+        MemoryToAccumulator => vec![accumulator, direct_address()],
+        AccumulatorToMemory => vec![direct_address(), accumulator],
+        InFixedPort => vec![accumulator, Operand::Immediate(ValueEnum::ByteSize(second_byte))],
+        InVariablePort => vec![accumulator, Operand::Register(Register::DX)],
+        OutFixedPort => vec![Operand::Immediate(ValueEnum::ByteSize(second_byte)), accumulator],
+        OutVariablePort => vec![Operand::Register(Register::DX), accumulator],
+        // The string instructions, the decimal adjusts without a base and the rest don't have operands that are encoded in the instruction.
+        _ => vec![],
+    };
+
+    return Ok(Instruction{
+        opcode: instruction,
+        mnemonic,
+        width: if is_word_size { OperandWidth::Word } else { OperandWidth::Byte },
+        prefixes,
+        length,
+        operands,
+        far,
+    })
+}
+
+/// Disassembles the whole binary, one instruction per line. The bytes that can't be decoded are emitted as `db 0xNN` and
+/// the disassembly continues from the next byte, so data in the middle of the code does not stop it.
+pub fn disassemble(binary_contents: &[u8]) -> Vec<String> {
+    let mut offset: usize = 0;
+    let mut lines: Vec<String> = Vec::new();
+    while offset < binary_contents.len() {
+        match decode(binary_contents, offset) {
+            Ok(instruction) => {
+                lines.push(instruction.to_string());
+                offset += instruction.length;
+            },
+            Err(_) => {
+                lines.push(format!("db 0x{:02x}", binary_contents[offset]));
+                offset += 1;
+            },
+        }
+    }
+    return lines
+}
+
+// The base and the index register the R/M field of a memory operand points to, the direct addresses don't have either.
+fn get_base_and_index_registers(second_byte: u8, memory_mode: MemoryModeEnum) -> (Option<Register>, Option<Register>) {
+    if memory_mode == DirectMemoryOperation || memory_mode == RegisterMode {
+        return (None, None);
+    }
+    return match second_byte & Masks::RM_BITS as u8 {
+        0b000 => (Some(Register::BX), Some(Register::SI)),
+        0b001 => (Some(Register::BX), Some(Register::DI)),
+        0b010 => (Some(Register::BP), Some(Register::SI)),
+        0b011 => (Some(Register::BP), Some(Register::DI)),
+        0b100 => (None, Some(Register::SI)),
+        0b101 => (None, Some(Register::DI)),
+        0b110 => (Some(Register::BP), None),
+        _ => (Some(Register::BX), None),
+    }
+}
+
+// Decodes the memory operand from the second byte and the displacement bytes after it. The 8-bit displacement is signed,
+// [bp - 2] for example is encoded as 0xFE.
+fn decode_effective_address(binary_contents: &[u8], ip: usize, memory_mode: MemoryModeEnum, segment_override: Option<SegmentRegister>) -> EffectiveAddress {
+    let (base, index) = get_base_and_index_registers(binary_contents[ip + 1], memory_mode);
+    let displacement = match memory_mode {
+        MemoryMode8Bit => Some(get_displacement(binary_contents, ip, memory_mode) as u8 as i8 as i16),
+        MemoryMode16Bit | DirectMemoryOperation => Some(get_displacement(binary_contents, ip, memory_mode) as u16 as i16),
+        _ => None,
+    };
+    return EffectiveAddress{base, index, displacement, segment: segment_override}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_listing_0038() {
+        let binary_contents = fs::read("/Users/rase/dev/intel8086-decoder/computer_enhance/perfaware/part1/listing_0038_many_register_mov").unwrap();
+
+        // Disassembling does not need the CPU, only the bytes.
+        let mut offset: usize = 0;
+        let mut decoded_instructions: Vec<String> = Vec::new();
+        while offset < binary_contents.len() {
+            let instruction = decode(&binary_contents, offset).unwrap();
+            offset += instruction.length;
+            decoded_instructions.push(instruction.to_string());
+        }
+        let expected_decoded_instructions = "mov cx, bx\nmov ch, ah\nmov dx, bx\nmov si, bx\nmov bx, di\nmov al, cl\nmov ch, ch\nmov bx, ax\nmov bx, si\nmov sp, di\nmov bp, ax";
+        assert_eq!(decoded_instructions.join("\n"), expected_decoded_instructions);
+    }
+
+    #[test]
+    fn test_listing_0041() {
+        let binary_contents = fs::read("/Users/rase/dev/intel8086-decoder/computer_enhance/perfaware/part1/listing_0041_add_sub_cmp_jnz").unwrap();
+        let mut offset: usize = 0;
+        let mut decoded_instructions: Vec<String> = Vec::new();
+        while offset < binary_contents.len() {
+            let instruction = decode(&binary_contents, offset).unwrap();
+            offset += instruction.length;
+            decoded_instructions.push(instruction.to_string());
+        }
+
+        let expected_instructions: Vec<&str> = vec![
+            // add instructions
+            "add bx, [bx + si]",
+            "add bx, [bp + 0]",
+            "add si, 2",
+            "add bp, 2",
+            "add cx, 8",
+            "add bx, [bp + 0]",
+            "add cx, [bx + 2]",
+            "add bh, [bp + si + 4]",
+            "add di, [bp + di + 6]",
+            "add [bx + si], bx",
+            "add [bp + 0], bx",
+            "add [bp + 0], bx",
+            "add [bx + 2], cx",
+            "add [bp + si + 4], bh",
+            "add [bp + di + 6], di",
+            "add byte [bx], 34",
+            "add word [bp + si + 1000], 29",
+            "add ax, [bp + 0]",
+            "add al, [bx + si]",
+            "add ax, bx",
+            "add al, ah",
+            "add ax, 1000",
+            "add al, -30",
+            "add al, 9",
+
+            // sub instructions
+            "sub bx, [bx + si]",
+            "sub bx, [bp + 0]",
+            "sub si, 2",
+            "sub bp, 2",
+            "sub cx, 8",
+            "sub bx, [bp + 0]",
+            "sub cx, [bx + 2]",
+            "sub bh, [bp + si + 4]",
+            "sub di, [bp + di + 6]",
+            "sub [bx + si], bx",
+            "sub [bp + 0], bx",
+            "sub [bp + 0], bx",
+            "sub [bx + 2], cx",
+            "sub [bp + si + 4], bh",
+            "sub [bp + di + 6], di",
+            "sub byte [bx], 34",
+            "sub word [bx + di], 29",
+            "sub ax, [bp + 0]",
+            "sub al, [bx + si]",
+            "sub ax, bx",
+            "sub al, ah",
+            "sub ax, 1000",
+            "sub al, -30",
+            "sub al, 9",
+
+            // cmp instructions
+            "cmp bx, [bx + si]",
+            "cmp bx, [bp + 0]",
+            "cmp si, 2",
+            "cmp bp, 2",
+            "cmp cx, 8",
+            "cmp bx, [bp + 0]",
+            "cmp cx, [bx + 2]",
+            "cmp bh, [bp + si + 4]",
+            "cmp di, [bp + di + 6]",
+            "cmp [bx + si], bx",
+            "cmp [bp + 0], bx",
+            "cmp [bp + 0], bx",
+            "cmp [bx + 2], cx",
+            "cmp [bp + si + 4], bh",
+            "cmp [bp + di + 6], di",
+            "cmp byte [bx], 34",
+            "cmp word [4834], 29",
+            "cmp ax, [bp + 0]",
+            "cmp al, [bx + si]",
+            "cmp ax, bx",
+            "cmp al, ah",
+            "cmp ax, 1000",
+            "cmp al, -30",
+            "cmp al, 9",
+
+            // labels and jump instructions
+            "jnz 4", // the forward jump over the next jnz.
+            "jnz -2",
+            "jnz -4",
+            "jnz -2",
+            "je 0",
+            "jl -2",
+            "jle -4",
+            "jb -6",
+            "jbe -8",
+            "jp -10",
+            "jo -12",
+            "js -14",
+            "jnz -16",
+            "jnl -18",
+            "jg -20",
+            "jnb -22",
+            "ja -24",
+            "jnp -26",
+            "jno -28",
+            "jns -30",
+            "loop -32",
+            "loopz -34",
+            "loopnz -36",
+            "jcxz -38",
+        ];
+
+        for (index, instruction) in decoded_instructions.iter().enumerate() {
+            assert_eq!(*instruction, expected_instructions[index].to_string());
+        }
+        // assert_eq!(decoded_instructions, expected_instructions);
+    }
+
+    #[test]
+    fn test_structured_instructions() {
+        let decode_bytes = |binary_contents: Vec<u8>| decode(&binary_contents, 0).unwrap();
+
+        let instruction = decode_bytes(vec![0x88, 0x4a, 0x04]);
+        let address = EffectiveAddress{base: Some(Register::BP), index: Some(Register::SI), displacement: Some(4), segment: None};
+        assert_eq!(instruction.operands, vec![Operand::Memory(address), Operand::Register(Register::CL)]);
+        assert_eq!((instruction.mnemonic, instruction.width, instruction.length), (Mnemonic::Mov, OperandWidth::Byte, 3));
+        assert_eq!(address.segment_register(), SegmentRegister::SS);
+        assert_eq!(instruction.to_string(), "mov [bp + si + 4], cl");
+
+        // The 8-bit displacement is sign extended and the segment override ends up in the address.
+        let instruction = decode_bytes(vec![0x26, 0xc7, 0x41, 0xfe, 0xe8, 0x03]);
+        let address = EffectiveAddress{base: Some(Register::BX), index: Some(Register::DI), displacement: Some(-2), segment: Some(SegmentRegister::ES)};
+        assert_eq!(instruction.operands, vec![Operand::Memory(address), Operand::Immediate(ValueEnum::WordSize(1000))]);
+        assert_eq!((instruction.opcode, instruction.prefixes.size, instruction.length), (ImmediateToRegisterMemory, 1, 6));
+        assert_eq!(address.segment_register(), SegmentRegister::ES);
+        assert_eq!(instruction.to_string(), "mov word es:[bx + di - 2], 1000");
+
+        let instruction = decode_bytes(vec![0x75, 0xfc]);
+        assert_eq!(instruction.operands, vec![Operand::RelativeTarget(-4)]);
+        assert_eq!(instruction.to_string(), "jnz -2");
+
+        let instruction = decode_bytes(vec![0x9a, 0x78, 0x56, 0x34, 0x12]);
+        assert_eq!(instruction.operands, vec![Operand::FarPointer{segment: 0x1234, offset: 0x5678}]);
+        assert!(instruction.far);
+        assert_eq!(instruction.to_string(), "call 4660:22136");
+
+        let instruction = decode_bytes(vec![0xff, 0x1c]);
+        assert_eq!(instruction.operands, vec![Operand::Memory(EffectiveAddress{base: None, index: Some(Register::SI), displacement: None, segment: None})]);
+        assert!(instruction.far);
+        assert_eq!(instruction.to_string(), "call far [si]");
+
+        // The REG field 001 of 0xF6 and 0xF7 is an undocumented alias of test.
+        let instruction = decode_bytes(vec![0xf6, 0xc9, 0x05]);
+        assert_eq!((instruction.opcode, instruction.length), (ImmediateToRegisterMemory, 3));
+        assert_eq!(instruction.to_string(), "test cl, 5");
+
+        // The prefixes that don't do anything with the instruction are kept in the output.
+        let instruction = decode_bytes(vec![0x26, 0x90]);
+        assert_eq!((instruction.prefixes.segment_override, instruction.length), (Some(SegmentRegister::ES), 2));
+        assert_eq!(instruction.to_string(), "es nop");
+        assert_eq!(decode_bytes(vec![0xf3, 0x90]).to_string(), "rep nop");
+        assert_eq!(decode_bytes(vec![0xf2, 0x2e, 0x01, 0xd8]).to_string(), "repne cs add ax, bx");
+
+        let instruction = decode_bytes(vec![0xe4, 0x60]);
+        assert_eq!(instruction.operands, vec![Operand::Register(Register::AL), Operand::Immediate(ValueEnum::ByteSize(0x60))]);
+        assert_eq!(instruction.to_string(), "in al, 96");
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode(&[0x90, 0x60], 1), Err(DecodeError::UnknownOpcode{offset: 1, opcode: 0x60}));
+        assert_eq!(decode(&[0xf0, 0xd6], 0), Err(DecodeError::UnknownOpcode{offset: 0, opcode: 0xd6}));
+
+        // mov cx, 3 without the high byte of the immediate.
+        assert_eq!(decode(&[0xb9, 0x03], 0), Err(DecodeError::TruncatedInstruction{offset: 0, length: 3, available: 2}));
+        // add word [bp + si + 1000], 29 that ends in the middle of the displacement.
+        assert_eq!(decode(&[0x83, 0x82, 0xe8], 0), Err(DecodeError::TruncatedInstruction{offset: 0, length: 5, available: 3}));
+        // The ModRM byte is missing.
+        assert_eq!(decode(&[0x01], 0), Err(DecodeError::TruncatedInstruction{offset: 0, length: 2, available: 1}));
+        // A prefix without an instruction after it.
+        assert_eq!(decode(&[0xf3], 0), Err(DecodeError::TruncatedInstruction{offset: 0, length: 2, available: 1}));
+        assert_eq!(decode(&[0x90], 1), Err(DecodeError::TruncatedInstruction{offset: 1, length: 1, available: 0}));
+
+        // mov with an immediate only has the REG field 000, 0xFE is only inc and dec and 0xFF doesn't have 111.
+        assert_eq!(decode(&[0xc6, 0x08, 0x01], 0), Err(DecodeError::InvalidModRM{offset: 0, opcode: 0xc6, mod_rm: 0x08}));
+        assert_eq!(decode(&[0xfe, 0x10], 0), Err(DecodeError::InvalidModRM{offset: 0, opcode: 0xfe, mod_rm: 0x10}));
+        assert_eq!(decode(&[0xff, 0x38], 0), Err(DecodeError::InvalidModRM{offset: 0, opcode: 0xff, mod_rm: 0x38}));
+        assert_eq!(decode(&[0x8f, 0x08], 0), Err(DecodeError::InvalidModRM{offset: 0, opcode: 0x8f, mod_rm: 0x08}));
+        // lea and the far indirect calls need a memory operand.
+        assert_eq!(decode(&[0x8d, 0xc3], 0), Err(DecodeError::InvalidModRM{offset: 0, opcode: 0x8d, mod_rm: 0xc3}));
+        assert_eq!(decode(&[0xff, 0xdb], 0), Err(DecodeError::InvalidModRM{offset: 0, opcode: 0xff, mod_rm: 0xdb}));
+        // The segment register movs with the REG field 1xx, these are not mov ax, es and mov ds, ax.
+        assert_eq!(decode(&[0x8c, 0xe0], 0), Err(DecodeError::InvalidModRM{offset: 0, opcode: 0x8c, mod_rm: 0xe0}));
+        assert_eq!(decode(&[0x8e, 0xf8], 0), Err(DecodeError::InvalidModRM{offset: 0, opcode: 0x8e, mod_rm: 0xf8}));
+
+        assert_eq!(DecodeError::UnknownOpcode{offset: 4, opcode: 0x60}.to_string(), "unknown opcode 0x60 at offset 4");
+    }
+
+    // The opcode map of the 8086 manual, written by the byte value so it does not share anything with construct_opcode_table.
+    fn expected_opcode(first_byte: u8, reg: u8) -> Option<(InstructionType, Mnemonic)> {
+        use crate::bits::InstructionType::*;
+        let arithmetic = [Mnemonic::Add, Mnemonic::Or, Mnemonic::Adc, Mnemonic::Sbb, Mnemonic::And, Mnemonic::Sub, Mnemonic::Xor, Mnemonic::Cmp];
+        let immediate_to_accumulator = [ImmediateToAccumulatorADD, ImmediateToAccumulatorOR, ImmediateToAccumulatorADC, ImmediateToAccumulatorSBB,
+            ImmediateToAccumulatorAND, ImmediateToAccumulatorSUB, ImmediateToAccumulatorXOR, ImmediateToAccumulatorCMP];
+        let conditional_jumps = [
+            (JO_JUMP, Mnemonic::Jo), (JNO_JUMP, Mnemonic::Jno), (JB_JUMP, Mnemonic::Jb), (JNB_JUMP, Mnemonic::Jnb),
+            (JE_JUMP, Mnemonic::Je), (JNE_JUMP, Mnemonic::Jnz), (JBE_JUMP, Mnemonic::Jbe), (JNBE_JUMP, Mnemonic::Ja),
+            (JS_JUMP, Mnemonic::Js), (JNS, Mnemonic::Jns), (JP_JUMP, Mnemonic::Jp), (JNP_JUMP, Mnemonic::Jnp),
+            (JL_JUMP, Mnemonic::Jl), (JNL_JUMP, Mnemonic::Jnl), (JLE_JUMP, Mnemonic::Jle), (JNLE_JUMP, Mnemonic::Jg),
+        ];
+        let shifts_and_rotates = [Mnemonic::Rol, Mnemonic::Ror, Mnemonic::Rcl, Mnemonic::Rcr, Mnemonic::Shl, Mnemonic::Shr, Mnemonic::Shl, Mnemonic::Sar];
+        let single_operand = [Mnemonic::Test, Mnemonic::Test, Mnemonic::Not, Mnemonic::Neg, Mnemonic::Mul, Mnemonic::Imul, Mnemonic::Div, Mnemonic::Idiv];
+        let increment_decrement_word = [Some(Mnemonic::Inc), Some(Mnemonic::Dec), Some(Mnemonic::Call), Some(Mnemonic::Call),
+            Some(Mnemonic::Jmp), Some(Mnemonic::Jmp), Some(Mnemonic::Push), None];
+        let operation = (first_byte >> 3) as usize;
+
+        return match first_byte {
+            // 00ooo0dw and 00ooo10w are the register/memory and the immediate to accumulator forms of the eight operations.
+            0x00..=0x3f if first_byte & 0b111 <= 0b011 => Some((RegisterMemory, arithmetic[operation])),
+            0x00..=0x3f if first_byte & 0b110 == 0b100 => Some((immediate_to_accumulator[operation], arithmetic[operation])),
+            0x06 | 0x0e | 0x16 | 0x1e => Some((PushSegmentRegister, Mnemonic::Push)),
+            0x07 | 0x0f | 0x17 | 0x1f => Some((PopSegmentRegister, Mnemonic::Pop)),
+            0x27 => Some((DAA, Mnemonic::Daa)),
+            0x2f => Some((DAS, Mnemonic::Das)),
+            0x37 => Some((AAA, Mnemonic::Aaa)),
+            0x3f => Some((AAS, Mnemonic::Aas)),
+            0x40..=0x47 => Some((IncrementRegister, Mnemonic::Inc)),
+            0x48..=0x4f => Some((DecrementRegister, Mnemonic::Dec)),
+            0x50..=0x57 => Some((PushRegister, Mnemonic::Push)),
+            0x58..=0x5f => Some((PopRegister, Mnemonic::Pop)),
+            0x70..=0x7f => Some(conditional_jumps[(first_byte & 0xf) as usize]),
+            0x80..=0x83 => Some((ImmediateToRegisterMemory, arithmetic[reg as usize])),
+            0x84 | 0x85 => Some((RegisterMemory, Mnemonic::Test)),
+            0x86 | 0x87 => Some((ExchangeRegisterMemory, Mnemonic::Xchg)),
+            0x88..=0x8c | 0x8e => Some((RegisterMemory, Mnemonic::Mov)),
+            0x8d => Some((LEA, Mnemonic::Lea)),
+            0x8f if reg == 0 => Some((SingleOperandRegisterMemory, Mnemonic::Pop)),
+            0x90 => Some((NOP, Mnemonic::Nop)),
+            0x91..=0x97 => Some((ExchangeRegisterWithAccumulator, Mnemonic::Xchg)),
+            0x98 => Some((CBW, Mnemonic::Cbw)),
+            0x99 => Some((CWD, Mnemonic::Cwd)),
+            0x9a => Some((CALL_FAR, Mnemonic::Call)),
+            0x9b => Some((WAIT, Mnemonic::Wait)),
+            0x9c => Some((PUSHF, Mnemonic::Pushf)),
+            0x9d => Some((POPF, Mnemonic::Popf)),
+            0x9e => Some((SAHF, Mnemonic::Sahf)),
+            0x9f => Some((LAHF, Mnemonic::Lahf)),
+            0xa0 | 0xa1 => Some((MemoryToAccumulator, Mnemonic::Mov)),
+            0xa2 | 0xa3 => Some((AccumulatorToMemory, Mnemonic::Mov)),
+            0xa4 | 0xa5 => Some((StringOperation, Mnemonic::Movs)),
+            0xa6 | 0xa7 => Some((StringOperation, Mnemonic::Cmps)),
+            0xa8 | 0xa9 => Some((ImmediateToAccumulatorTEST, Mnemonic::Test)),
+            0xaa | 0xab => Some((StringOperation, Mnemonic::Stos)),
+            0xac | 0xad => Some((StringOperation, Mnemonic::Lods)),
+            0xae | 0xaf => Some((StringOperation, Mnemonic::Scas)),
+            0xb0..=0xbf => Some((ImmediateToRegisterMOV, Mnemonic::Mov)),
+            0xc2 => Some((RET_NEAR_IMMEDIATE, Mnemonic::Ret)),
+            0xc3 => Some((RET_NEAR, Mnemonic::Ret)),
+            0xc4 => Some((LES, Mnemonic::Les)),
+            0xc5 => Some((LDS, Mnemonic::Lds)),
+            0xc6 | 0xc7 if reg == 0 => Some((ImmediateToRegisterMemory, Mnemonic::Mov)),
+            0xca => Some((RET_FAR_IMMEDIATE, Mnemonic::Retf)),
+            0xcb => Some((RET_FAR, Mnemonic::Retf)),
+            0xcc => Some((INT3, Mnemonic::Int3)),
+            0xcd => Some((INT, Mnemonic::Int)),
+            0xce => Some((INTO, Mnemonic::Into)),
+            0xcf => Some((IRET, Mnemonic::Iret)),
+            0xd0..=0xd3 => Some((ShiftOrRotate, shifts_and_rotates[reg as usize])),
+            0xd4 => Some((AAM, Mnemonic::Aam)),
+            0xd5 => Some((AAD, Mnemonic::Aad)),
+            0xd7 => Some((XLAT, Mnemonic::Xlat)),
+            0xe0 => Some((LOOPNZ, Mnemonic::Loopnz)),
+            0xe1 => Some((LOOPZ, Mnemonic::Loopz)),
+            0xe2 => Some((LOOP, Mnemonic::Loop)),
+            0xe3 => Some((JCXZ, Mnemonic::Jcxz)),
+            0xe4 | 0xe5 => Some((InFixedPort, Mnemonic::In)),
+            0xe6 | 0xe7 => Some((OutFixedPort, Mnemonic::Out)),
+            0xe8 => Some((CALL_NEAR, Mnemonic::Call)),
+            0xe9 => Some((JMP_NEAR, Mnemonic::Jmp)),
+            0xea => Some((JMP_FAR, Mnemonic::Jmp)),
+            0xeb => Some((JMP_SHORT, Mnemonic::Jmp)),
+            0xec | 0xed => Some((InVariablePort, Mnemonic::In)),
+            0xee | 0xef => Some((OutVariablePort, Mnemonic::Out)),
+            0xf4 => Some((HLT, Mnemonic::Hlt)),
+            0xf5 => Some((CMC, Mnemonic::Cmc)),
+            0xf6 | 0xf7 if reg <= 1 => Some((ImmediateToRegisterMemory, single_operand[reg as usize])),
+            0xf6 | 0xf7 => Some((SingleOperandRegisterMemory, single_operand[reg as usize])),
+            0xf8 => Some((CLC, Mnemonic::Clc)),
+            0xf9 => Some((STC, Mnemonic::Stc)),
+            0xfa => Some((CLI, Mnemonic::Cli)),
+            0xfb => Some((STI, Mnemonic::Sti)),
+            0xfc => Some((CLD, Mnemonic::Cld)),
+            0xfd => Some((STD, Mnemonic::Std)),
+            0xfe if reg <= 1 => Some((SingleOperandRegisterMemory, [Mnemonic::Inc, Mnemonic::Dec][reg as usize])),
+            0xff => increment_decrement_word[reg as usize].map(|mnemonic| (SingleOperandRegisterMemory, mnemonic)),
+            // The prefixes, the 80186 instructions, the escape to the coprocessor and the undocumented opcodes.
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_opcode_table() {
+        // Every first byte with every REG field, the groups are the only ones where the REG field changes the instruction.
+        for first_byte in 0..=255u8 {
+            for reg in 0..8u8 {
+                assert_eq!(lookup_opcode(first_byte, reg << 3), expected_opcode(first_byte, reg), "first_byte: 0x{:02x}, reg: {:03b}", first_byte, reg);
+            }
+        }
+        // The groups are entries of their own and not eight copies of an instruction.
+        for first_byte in [0x80, 0x81, 0x82, 0x83, 0x8f, 0xc6, 0xc7, 0xd0, 0xd1, 0xd2, 0xd3, 0xf6, 0xf7, 0xfe, 0xff] {
+            assert!(matches!(OPCODE_TABLE[first_byte as usize], OpCodeEntry::Group(_)), "first_byte: 0x{:02x}", first_byte);
+        }
+    }
+
+    #[test]
+    fn test_disassemble_undecodable_bytes() {
+        // The unknown opcode and the truncated mov at the end are emitted as data, the nop between them is still decoded.
+        let binary_contents = [0x60, 0x90, 0x8b, 0xd8, 0xb9, 0x03];
+        assert_eq!(disassemble(&binary_contents), vec!["db 0x60", "nop", "mov bx, ax", "db 0xb9", "db 0x03"]);
+    }
+}
@@ -1,79 +1,76 @@
-use crate::registers::ValueEnum;
+use bitflags::bitflags;
+use crate::registers::{ValueEnum, carry_and_overflow};
+use crate::instruction::Mnemonic;
 
-#[derive(Copy, Clone)]
-pub struct FlagRegister {
-    pub register: &'static str,
-    pub is_set: bool,
+bitflags! {
+    // The 16-bit FLAGS register of the 8086, the bits that are not listed here are unused.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct FlagRegister: u16 {
+        const CF = 0x0001;
+        const PF = 0x0004;
+        const AF = 0x0010;
+        const ZF = 0x0040;
+        const SF = 0x0080;
+        const TF = 0x0100;
+        const IF = 0x0200;
+        const DF = 0x0400;
+        const OF = 0x0800;
+    }
 }
 
-pub fn construct_flag_registers() -> [FlagRegister; 2] {
-    let flags = [
-        // FlagRegister { register: "CF", is_set: false, mask: 0x0001 },
-        // FlagRegister { register: "PF", is_set: false, mask: 0x0004 },
-        // FlagRegister { register: "AF", is_set: false, mask: 0x0010 },
-        FlagRegister { register: "ZF", is_set: false, /*mask: 0x0040 */},
-        FlagRegister { register: "SF", is_set: false, /*mask: 0x0080 */},
-        // FlagRegister { register: "TF", is_set: false, mask: 0x0100 },
-        // FlagRegister { register: "IF", is_set: false, mask: 0x0200 },
-        // FlagRegister { register: "DF", is_set: false, mask: 0x0400 },
-        // FlagRegister { register: "OF", is_set: false, mask: 0x0800 },
-        // FlagRegister { register: "IOPL", is_set: false, mask: 0x3000 },
-        // FlagRegister { register: "NT", is_set: false, mask: 0x4000 },
-        // FlagRegister { register: "MD", is_set: false, mask: 0x8000 },
-        // FlagRegister { register: "RF", is_set: false, mask: 0x0001_0000 },
-        // FlagRegister { register: "VM", is_set: false, mask: 0x0002_0000 },
-        // FlagRegister { register: "AC", is_set: false, mask: 0x0004_0000 },
-        // FlagRegister { register: "VIF", is_set: false, mask: 0x0008_0000 },
-        // FlagRegister { register: "VIP", is_set: false, mask: 0x0010_0000 },
-        // FlagRegister { register: "ID", is_set: false, mask: 0x0020_0000 },
-        // FlagRegister { register: "AI", is_set: false, mask: 0x8000_0000 },
-    ];
-    return flags;
+pub fn construct_flag_registers() -> FlagRegister {
+    return FlagRegister::empty();
 }
 
-pub fn flag_register_is_set(flag: &'static str, flag_registers: &mut [FlagRegister]) -> bool {
-    for flag_register in flag_registers.iter_mut() {
-        if flag_register.register == flag {
-            return flag_register.is_set;
-        }
-    }
-    panic!("Flag {} not found", flag);
+// The FLAGS word that pushf and the interrupts push. The 8086 always reads the bits 12-15 as 1, software checks them
+// to tell an 8086 apart from the later processors.
+pub fn get_flags_word(flag_registers: &FlagRegister) -> u16 {
+    return flag_registers.bits() | 0xF000
 }
 
-pub fn set_is_set_for_flag_register(flag: &'static str, flag_registers: &mut [FlagRegister], value: bool) -> () {
-    for flag_register in flag_registers.iter_mut() {
-        if flag_register.register == flag {
-                flag_register.is_set = value;
-                return
-            }
-        }
-    panic!("Flag {} not found", flag);
+// Sets the flags that only depend on the result of the operation.
+pub fn set_result_flags(result: ValueEnum, flag_registers: &mut FlagRegister) -> () {
+    flag_registers.set(FlagRegister::ZF, result.get_usize() == 0);
+    flag_registers.set(FlagRegister::SF, number_is_signed(result));
+    flag_registers.set(FlagRegister::PF, low_byte_has_even_parity(result));
 }
 
-pub fn set_flags(destination_value: ValueEnum, flag_registers: &mut [FlagRegister; 2]) -> () {
-    let destination_value_integer = destination_value.get_usize();
+// Sets the flags for the additions and subtractions. The carry and overflow can't be determined from the result alone
+// so wrap_add and wrap_sub return them, the auxiliary carry is calculated from the operands.
+pub fn set_flags(destination: ValueEnum, source: ValueEnum, result: ValueEnum, carry_and_overflow: carry_and_overflow, flag_registers: &mut FlagRegister) -> () {
+    flag_registers.set(FlagRegister::CF, carry_and_overflow.carry);
+    flag_registers.set(FlagRegister::OF, carry_and_overflow.overflow);
+    // The auxiliary carry is the carry (or borrow) out of the lowest nibble.
+    flag_registers.set(FlagRegister::AF, (destination.get_usize() ^ source.get_usize() ^ result.get_usize()) & 0x10 != 0);
+    set_result_flags(result, flag_registers);
+}
 
-    if destination_value_integer == 0 {
-        set_is_set_for_flag_register("ZF", flag_registers, true);
-    } else {
-        set_is_set_for_flag_register("ZF", flag_registers, false);
+// The shifts and rotates set CF to the last bit that was shifted out, the shifts also set the flags based on the result.
+// The rotates only touch CF and OF. AF is undefined after the shifts so we leave it alone.
+pub fn set_shift_or_rotate_flags(mnemonic: Mnemonic, result: ValueEnum, carry_and_overflow: carry_and_overflow, flag_registers: &mut FlagRegister) -> () {
+    flag_registers.set(FlagRegister::CF, carry_and_overflow.carry);
+    flag_registers.set(FlagRegister::OF, carry_and_overflow.overflow);
+    if mnemonic == Mnemonic::Shl || mnemonic == Mnemonic::Shr || mnemonic == Mnemonic::Sar {
+        set_result_flags(result, flag_registers);
     }
+}
 
-    if number_is_signed(destination_value) {
-        set_is_set_for_flag_register("SF", flag_registers, true);
-    } else {
-        set_is_set_for_flag_register("SF", flag_registers, false);
-    }
+// The logical operations (and, or, xor, test) always clear CF and OF, the rest of the flags are set based on the result.
+// AF is undefined after the logical operations, we clear it like the hardware does.
+pub fn set_logical_operation_flags(result: ValueEnum, flag_registers: &mut FlagRegister) -> () {
+    set_result_flags(result, flag_registers);
+    flag_registers.remove(FlagRegister::CF | FlagRegister::OF | FlagRegister::AF);
 }
 
-pub fn get_all_currently_set_flags(flag_registers: &mut [FlagRegister; 2]) -> Vec<&'static str> {
-    let mut flags: Vec<&str> = Vec::with_capacity(flag_registers.len());
-    for flag_register in flag_registers.iter() {
-        if flag_register.is_set {
-            flags.push(flag_register.register);
-        }
-    }
-    return flags;
+// PF is set when the lowest byte of the result has an even amount of bits set, the upper byte does not matter even with 16-bit values.
+pub fn low_byte_has_even_parity(value: ValueEnum) -> bool {
+    let low_byte = (value.get_usize() & 0xFF) as u8;
+    return low_byte.count_ones().is_multiple_of(2)
+}
+
+// Returns the names of the flags that are currently set, in the order they are in the FLAGS register.
+pub fn get_all_currently_set_flags(flag_registers: &FlagRegister) -> Vec<&'static str> {
+    return flag_registers.iter_names().map(|(name, _)| name).collect();
 }
 
 pub fn number_is_signed(value: ValueEnum) -> bool {
@@ -97,13 +94,3 @@ fn get_highest_bit(value: ValueEnum) -> usize {
         panic!("Should not get here.")
     }
 }
-
-pub fn clear_flags_registers(flag_registers: &mut [FlagRegister]) -> () {
-    for flag_register in flag_registers.iter_mut() {
-        flag_register.is_set = false;
-    }
-}
-
-pub fn twos_complement(num: u8) -> i8 {
-    (!num).wrapping_add(1) as i8
-}
@@ -0,0 +1,419 @@
+use std::fmt;
+use crate::bits::{InstructionType, instruction_is_immediate_to_accumulator};
+use crate::bits::InstructionType::*;
+use crate::flag_registers::number_is_signed;
+use crate::registers::{Register, SegmentRegister, Value, ValueEnum};
+
+/// The operation of an instruction, the same mnemonic can have many encodings. The conditional jumps have the names nasm prints.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mnemonic {
+    /// Copies the source to the destination.
+    Mov,
+    /// Adds the source to the destination.
+    Add,
+    /// Adds the source and the carry flag to the destination.
+    Adc,
+    /// Subtracts the source from the destination.
+    Sub,
+    /// Subtracts the source and the carry flag from the destination.
+    Sbb,
+    /// Subtracts the source from the destination without storing the result, only the flags change.
+    Cmp,
+    /// Bitwise and.
+    And,
+    /// Bitwise or.
+    Or,
+    /// Bitwise exclusive or.
+    Xor,
+    /// Bitwise and without storing the result, only the flags change.
+    Test,
+    /// Adds one, the carry flag does not change.
+    Inc,
+    /// Subtracts one, the carry flag does not change.
+    Dec,
+    /// Two's complement negation.
+    Neg,
+    /// Inverts every bit.
+    Not,
+    /// Unsigned multiply with al or ax.
+    Mul,
+    /// Signed multiply with al or ax.
+    Imul,
+    /// Unsigned divide of ax or dx:ax.
+    Div,
+    /// Signed divide of ax or dx:ax.
+    Idiv,
+    /// Pushes a word to ss:sp.
+    Push,
+    /// Pops a word from ss:sp.
+    Pop,
+    /// Pushes the flags.
+    Pushf,
+    /// Pops the flags.
+    Popf,
+    /// Jump if overflow.
+    Jo,
+    /// Jump if not overflow.
+    Jno,
+    /// Jump if below (carry).
+    Jb,
+    /// Jump if not below (no carry).
+    Jnb,
+    /// Jump if equal (zero).
+    Je,
+    /// Jump if not zero.
+    Jnz,
+    /// Jump if below or equal.
+    Jbe,
+    /// Jump if above.
+    Ja,
+    /// Jump if sign.
+    Js,
+    /// Jump if not sign.
+    Jns,
+    /// Jump if parity.
+    Jp,
+    /// Jump if not parity.
+    Jnp,
+    /// Jump if less.
+    Jl,
+    /// Jump if not less.
+    Jnl,
+    /// Jump if less or equal.
+    Jle,
+    /// Jump if greater.
+    Jg,
+    /// Decrements cx and jumps if cx is not zero and the zero flag is not set.
+    Loopnz,
+    /// Decrements cx and jumps if cx is not zero and the zero flag is set.
+    Loopz,
+    /// Decrements cx and jumps if cx is not zero.
+    Loop,
+    /// Jump if cx is zero.
+    Jcxz,
+    /// Pushes the return address and jumps, near or far.
+    Call,
+    /// Jumps, near or far.
+    Jmp,
+    /// Near return.
+    Ret,
+    /// Far return.
+    Retf,
+    /// Rotate left.
+    Rol,
+    /// Rotate right.
+    Ror,
+    /// Rotate left through the carry flag.
+    Rcl,
+    /// Rotate right through the carry flag.
+    Rcr,
+    /// Shift left.
+    Shl,
+    /// Logical shift right.
+    Shr,
+    /// Arithmetic shift right, the sign bit is kept.
+    Sar,
+    /// Copies ds:si to es:di.
+    Movs,
+    /// Compares ds:si with es:di.
+    Cmps,
+    /// Stores al or ax to es:di.
+    Stos,
+    /// Loads al or ax from ds:si.
+    Lods,
+    /// Compares al or ax with es:di.
+    Scas,
+    /// Decimal adjust after addition.
+    Daa,
+    /// Decimal adjust after subtraction.
+    Das,
+    /// ASCII adjust after addition.
+    Aaa,
+    /// ASCII adjust after subtraction.
+    Aas,
+    /// ASCII adjust after multiply.
+    Aam,
+    /// ASCII adjust before division.
+    Aad,
+    /// Sign extends al into ax.
+    Cbw,
+    /// Sign extends ax into dx:ax.
+    Cwd,
+    /// Stores ah into the low byte of the flags.
+    Sahf,
+    /// Loads the low byte of the flags into ah.
+    Lahf,
+    /// Clears the carry flag.
+    Clc,
+    /// Sets the carry flag.
+    Stc,
+    /// Complements the carry flag.
+    Cmc,
+    /// Clears the direction flag.
+    Cld,
+    /// Sets the direction flag.
+    Std,
+    /// Clears the interrupt flag.
+    Cli,
+    /// Sets the interrupt flag.
+    Sti,
+    /// Halts until an interrupt.
+    Hlt,
+    /// Waits for the coprocessor.
+    Wait,
+    /// Does nothing.
+    Nop,
+    /// Reads from an I/O port.
+    In,
+    /// Writes to an I/O port.
+    Out,
+    /// Loads the offset of the memory operand.
+    Lea,
+    /// Loads a far pointer into the register and ds.
+    Lds,
+    /// Loads a far pointer into the register and es.
+    Les,
+    /// Swaps the operands.
+    Xchg,
+    /// Loads al from ds:[bx + al].
+    Xlat,
+    /// Software interrupt.
+    Int,
+    /// Breakpoint interrupt.
+    Int3,
+    /// Interrupt 4 if the overflow flag is set.
+    Into,
+    /// Returns from an interrupt handler.
+    Iret,
+}
+
+impl Mnemonic {
+    /// The mnemonic like it's written in the assembly, the string instructions without the size suffix.
+    pub fn name(self) -> &'static str {
+        return match self {
+            Mnemonic::Mov => "mov", Mnemonic::Add => "add", Mnemonic::Adc => "adc", Mnemonic::Sub => "sub", Mnemonic::Sbb => "sbb",
+            Mnemonic::Cmp => "cmp", Mnemonic::And => "and", Mnemonic::Or => "or", Mnemonic::Xor => "xor", Mnemonic::Test => "test",
+            Mnemonic::Inc => "inc", Mnemonic::Dec => "dec", Mnemonic::Neg => "neg", Mnemonic::Not => "not",
+            Mnemonic::Mul => "mul", Mnemonic::Imul => "imul", Mnemonic::Div => "div", Mnemonic::Idiv => "idiv",
+            Mnemonic::Push => "push", Mnemonic::Pop => "pop", Mnemonic::Pushf => "pushf", Mnemonic::Popf => "popf",
+            Mnemonic::Jo => "jo", Mnemonic::Jno => "jno", Mnemonic::Jb => "jb", Mnemonic::Jnb => "jnb", Mnemonic::Je => "je",
+            Mnemonic::Jnz => "jnz", Mnemonic::Jbe => "jbe", Mnemonic::Ja => "ja", Mnemonic::Js => "js", Mnemonic::Jns => "jns",
+            Mnemonic::Jp => "jp", Mnemonic::Jnp => "jnp", Mnemonic::Jl => "jl", Mnemonic::Jnl => "jnl", Mnemonic::Jle => "jle",
+            Mnemonic::Jg => "jg", Mnemonic::Loopnz => "loopnz", Mnemonic::Loopz => "loopz", Mnemonic::Loop => "loop", Mnemonic::Jcxz => "jcxz",
+            Mnemonic::Call => "call", Mnemonic::Jmp => "jmp", Mnemonic::Ret => "ret", Mnemonic::Retf => "retf",
+            Mnemonic::Rol => "rol", Mnemonic::Ror => "ror", Mnemonic::Rcl => "rcl", Mnemonic::Rcr => "rcr",
+            Mnemonic::Shl => "shl", Mnemonic::Shr => "shr", Mnemonic::Sar => "sar",
+            Mnemonic::Movs => "movs", Mnemonic::Cmps => "cmps", Mnemonic::Stos => "stos", Mnemonic::Lods => "lods", Mnemonic::Scas => "scas",
+            Mnemonic::Daa => "daa", Mnemonic::Das => "das", Mnemonic::Aaa => "aaa", Mnemonic::Aas => "aas", Mnemonic::Aam => "aam",
+            Mnemonic::Aad => "aad", Mnemonic::Cbw => "cbw", Mnemonic::Cwd => "cwd", Mnemonic::Sahf => "sahf", Mnemonic::Lahf => "lahf",
+            Mnemonic::Clc => "clc", Mnemonic::Stc => "stc", Mnemonic::Cmc => "cmc", Mnemonic::Cld => "cld", Mnemonic::Std => "std",
+            Mnemonic::Cli => "cli", Mnemonic::Sti => "sti", Mnemonic::Hlt => "hlt", Mnemonic::Wait => "wait", Mnemonic::Nop => "nop",
+            Mnemonic::In => "in", Mnemonic::Out => "out", Mnemonic::Lea => "lea", Mnemonic::Lds => "lds", Mnemonic::Les => "les",
+            Mnemonic::Xchg => "xchg", Mnemonic::Xlat => "xlat",
+            Mnemonic::Int => "int", Mnemonic::Int3 => "int3", Mnemonic::Into => "into", Mnemonic::Iret => "iret",
+        }
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.name())
+    }
+}
+
+/// The repeat prefixes of the string instructions. With cmps and scas rep is repe (repeat while equal).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RepeatPrefix {
+    /// rep, or repe with cmps and scas.
+    Rep,
+    /// repne, only used with cmps and scas.
+    Repne,
+}
+
+impl fmt::Display for RepeatPrefix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", match self { RepeatPrefix::Rep => "rep", RepeatPrefix::Repne => "repne" })
+    }
+}
+
+/// The prefix bytes that come before the opcode.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InstructionPrefixes {
+    /// The segment register that replaces the default segment of the memory operand, es for example.
+    pub segment_override: Option<SegmentRegister>,
+    /// rep or repne, only the string instructions use these.
+    pub repeat: Option<RepeatPrefix>,
+    /// The lock prefix, it does not change what the instruction does.
+    pub lock: bool,
+    /// The amount of prefix bytes.
+    pub size: usize,
+}
+
+/// The size of the data the instruction works on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OperandWidth {
+    /// 8 bits.
+    Byte,
+    /// 16 bits.
+    Word,
+}
+
+/// A memory operand, the offset is base + index + displacement and it wraps around inside of the segment.
+/// The direct addresses don't have a base or an index, the address is the displacement.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EffectiveAddress {
+    /// bx or bp.
+    pub base: Option<Register>,
+    /// si or di.
+    pub index: Option<Register>,
+    /// None when the instruction did not have a displacement, [bp + 0] is encoded with one because [bp] is the direct address.
+    pub displacement: Option<i16>,
+    /// The segment override prefix, without it the segment is ss for bp based addresses and ds for everything else.
+    pub segment: Option<SegmentRegister>,
+}
+
+// Prints the address like nasm does, es:[bx + si - 4] for example.
+impl fmt::Display for EffectiveAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(segment_register) = self.segment {
+            write!(f, "{}:", segment_register)?;
+        }
+        let registers: Vec<&str> = [self.base, self.index].into_iter().flatten().map(Register::name).collect();
+        if registers.is_empty() {
+            return write!(f, "[{}]", self.displacement.unwrap_or(0) as u16);
+        }
+        match self.displacement {
+            Some(displacement) if displacement < 0 => write!(f, "[{} - {}]", registers.join(" + "), -(displacement as i32)),
+            Some(displacement) => write!(f, "[{} + {}]", registers.join(" + "), displacement),
+            None => write!(f, "[{}]", registers.join(" + ")),
+        }
+    }
+}
+
+impl EffectiveAddress {
+    /// The segment register the offset is relative to.
+    pub fn segment_register(&self) -> SegmentRegister {
+        return match self.segment {
+            Some(segment_register) => segment_register,
+            None if self.base == Some(Register::BP) => SegmentRegister::SS,
+            None => SegmentRegister::DS,
+        }
+    }
+}
+
+/// An operand of a decoded instruction.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Operand {
+    /// A general purpose or a segment register, the byte registers are separate from the word registers.
+    Register(Register),
+    /// The immediate is already sign extended to the width it's used with.
+    Immediate(ValueEnum),
+    /// A memory operand.
+    Memory(EffectiveAddress),
+    /// The signed displacement of a relative jump or call, it's relative to the end of the instruction.
+    RelativeTarget(i16),
+    /// The far calls and jumps have the segment and the offset in the instruction.
+    FarPointer {
+        /// The value cs gets.
+        segment: u16,
+        /// The value ip gets.
+        offset: u16,
+    },
+}
+
+/// A decoded instruction, the operands are in the order they are written in the assembly so the destination comes first.
+/// Display prints the instruction like nasm would.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instruction {
+    // How the instruction was encoded, the decoder and the formatting look at this.
+    pub(crate) opcode: InstructionType,
+    /// The operation, the simulator dispatches on this.
+    pub mnemonic: Mnemonic,
+    /// Byte or word, the instructions that always work on words (push, lea, the segment registers) are words.
+    pub width: OperandWidth,
+    /// The prefix bytes before the opcode.
+    pub prefixes: InstructionPrefixes,
+    /// The size of the instruction in bytes, the prefixes included.
+    pub length: usize,
+    /// The operands in the assembly order, the operands that are implied by the instruction (like al with xlat) are not here.
+    pub operands: Vec<Operand>,
+    /// The far calls, jumps and returns change cs too.
+    pub far: bool,
+}
+
+impl Instruction {
+    /// true when the instruction works on words.
+    pub fn is_word_size(&self) -> bool {
+        return self.width == OperandWidth::Word
+    }
+
+    // The memory operands need the size when there is no register operand to tell it, the far indirect calls and jumps say far instead.
+    fn memory_operand_needs_size(&self) -> bool {
+        return match self.opcode {
+            ImmediateToRegisterMemory | SingleOperandRegisterMemory | ShiftOrRotate | MemoryToAccumulator | AccumulatorToMemory => true,
+            // The direct addresses always have the size, mov with the memory as the destination only has it when there's no displacement.
+            RegisterMemory => self.operands.iter().any(|operand| matches!(operand, Operand::Memory(EffectiveAddress{base: None, index: None, ..})))
+                || (self.mnemonic == Mnemonic::Mov && self.is_word_size() && matches!(self.operands[0], Operand::Memory(EffectiveAddress{displacement: None, ..}))),
+            _ => false,
+        }
+    }
+
+    fn format_operand(&self, operand: &Operand) -> String {
+        return match operand {
+            Operand::Register(register) => register.to_string(),
+            // The data immediates are printed signed when the highest bit is set, the counts, ports and interrupt numbers are never negative.
+            Operand::Immediate(value) if matches!(self.opcode, ImmediateToRegisterMemory | ImmediateToRegisterMOV) || instruction_is_immediate_to_accumulator(self.opcode) => {
+                Value{value: *value, is_signed: number_is_signed(*value)}.get_string_number_from_bits()
+            },
+            Operand::Immediate(value) => value.get_usize().to_string(),
+            Operand::Memory(address) if self.far => format!("far {}", address),
+            Operand::Memory(address) if self.memory_operand_needs_size() => format!("{} {}", if self.is_word_size() { "word" } else { "byte" }, address),
+            Operand::Memory(address) => address.to_string(),
+            // The relative targets are printed as the offset from the start of the instruction, like nasm's $+offset.
+            Operand::RelativeTarget(displacement) => (*displacement as i32 + self.length as i32).to_string(),
+            Operand::FarPointer{segment, offset} => format!("{}:{}", segment, offset),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.prefixes.lock {
+            write!(f, "lock ")?;
+        }
+        if self.opcode == StringOperation {
+            let compares = self.mnemonic == Mnemonic::Cmps || self.mnemonic == Mnemonic::Scas;
+            match self.prefixes.repeat {
+                Some(RepeatPrefix::Rep) if compares => write!(f, "repe ")?,
+                Some(repeat) => write!(f, "{} ", repeat)?,
+                None => (),
+            }
+            if let Some(segment_register) = self.prefixes.segment_override {
+                write!(f, "{} ", segment_register)?;
+            }
+            return write!(f, "{}{}", self.mnemonic, if self.is_word_size() { "w" } else { "b" });
+        }
+        if let (XLAT, Some(segment_register)) = (self.opcode, self.prefixes.segment_override) {
+            return write!(f, "{} {}", segment_register, self.mnemonic);
+        }
+        // The prefixes that the instruction does not use are printed in front of it, es nop for example, so the bytes can still be told from the output.
+        if let Some(repeat) = self.prefixes.repeat {
+            write!(f, "{} ", repeat)?;
+        }
+        if let Some(segment_register) = self.prefixes.segment_override {
+            if !self.operands.iter().any(|operand| matches!(operand, Operand::Memory(_))) {
+                write!(f, "{} ", segment_register)?;
+            }
+        }
+        // The base of aam and aad is almost always 10 and then the assemblers don't print it.
+        if (self.opcode == AAM || self.opcode == AAD) && self.operands[0] == Operand::Immediate(ValueEnum::ByteSize(10)) {
+            return write!(f, "{}", self.mnemonic);
+        }
+
+        let operands: Vec<String> = self.operands.iter().map(|operand| self.format_operand(operand)).collect();
+        if operands.is_empty() {
+            return write!(f, "{}", self.mnemonic);
+        }
+        return write!(f, "{} {}", self.mnemonic, operands.join(", "));
+    }
+}
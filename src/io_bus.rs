@@ -0,0 +1,44 @@
+/// The 8086 has a separate 64 KiB address space for the I/O ports that in and out use.
+/// The simulator does not know what is connected to the ports so it asks the I/O bus, implement IoBus to attach devices.
+pub trait IoBus {
+    /// Returns the byte (or the word from the port and the port after it) that the device puts on the bus.
+    fn read_port(&mut self, port: u16, is_word_size: bool) -> u16;
+    /// Puts the byte (or the word to the port and the port after it) on the bus, only the low byte of the value is used with bytes.
+    fn write_port(&mut self, port: u16, value: u16, is_word_size: bool);
+}
+
+/// A read or a write that went through the LoggingIoBus.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IoAccess {
+    /// The port number that was accessed.
+    pub port: u16,
+    /// The value that was read or written, only the low byte is used with the byte sized accesses.
+    pub value: u16,
+    /// true for out and false for in.
+    pub is_write: bool,
+}
+
+/// The default I/O bus, there is nothing connected to it so it logs the accesses and the reads return 0xFF like
+/// the unmapped ports on a real machine where nothing drives the data lines.
+pub struct LoggingIoBus {
+    /// Every access in the order they happened.
+    pub accesses: Vec<IoAccess>,
+}
+
+/// A LoggingIoBus that has not been accessed yet.
+pub fn construct_io_bus() -> LoggingIoBus {
+    return LoggingIoBus{accesses: Vec::new()};
+}
+
+impl IoBus for LoggingIoBus {
+    fn read_port(&mut self, port: u16, is_word_size: bool) -> u16 {
+        let value = if is_word_size { 0xFFFF } else { 0xFF };
+        self.accesses.push(IoAccess{port, value, is_write: false});
+        return value
+    }
+
+    fn write_port(&mut self, port: u16, value: u16, is_word_size: bool) {
+        let value = if is_word_size { value } else { value & 0xFF };
+        self.accesses.push(IoAccess{port, value, is_write: true});
+    }
+}
@@ -0,0 +1,47 @@
+//! An Intel 8086 decoder and simulator.
+//!
+//! [`decode`] turns the bytes of a binary into an [`Instruction`] without looking at any CPU state, the instruction
+//! prints itself like nasm would. [`execute`] runs a decoded instruction on a [`Cpu`] and returns the trace of what it changed.
+//! The registers and the memory can be set up before the simulation and read back after it.
+//!
+//! ```
+//! use assembler_8086::{construct_cpu, construct_io_bus, decode, execute, Register};
+//!
+//! // mov bx, [0] / add bx, 4 / mov [2], bx / push bx
+//! let binary_contents = [0x8b, 0x1e, 0x00, 0x00, 0x83, 0xc3, 0x04, 0x89, 0x1e, 0x02, 0x00, 0x53];
+//! let mut io_bus = construct_io_bus();
+//! let mut cpu = construct_cpu(&mut io_bus);
+//! cpu.load_program(0x1000, &binary_contents);
+//! cpu.set_register_value(Register::DS, 0x2000);
+//! cpu.set_register_value(Register::SS, 0x3000);
+//! cpu.set_register_value(Register::SP, 0x100);
+//! cpu.write_memory(0x2000, 0, 3, true);
+//! while cpu.instruction_pointer < binary_contents.len() {
+//!     let instruction = decode(&binary_contents, cpu.instruction_pointer).unwrap();
+//!     let trace = execute(&mut cpu, &instruction);
+//!     println!("{} | {} -> {}", trace.formatted_instruction, trace.original_value.get_string_number_from_bits(), trace.updated_value.get_string_number_from_bits());
+//! }
+//! assert_eq!(cpu.register_value(Register::BX).value.get_usize(), 7);
+//! assert_eq!(cpu.read_memory(0x2000, 2, true).value.get_usize(), 7);
+//! assert_eq!(cpu.read_physical_memory(0x300FE, true).value.get_usize(), 7);
+//! assert_eq!(cpu.read_physical_memory(0x10000, false).value.get_usize(), 0x8b);
+//! ```
+
+pub(crate) mod bits;
+pub(crate) mod registers;
+pub(crate) mod flag_registers;
+pub(crate) mod memory;
+pub(crate) mod io_bus;
+pub(crate) mod instruction;
+pub(crate) mod cpu;
+mod decoder;
+mod simulator;
+
+pub use decoder::{decode, disassemble, DecodeError};
+#[doc(hidden)]
+pub use decoder::lookup_opcode_mnemonic;
+pub use simulator::{execute, InstructionTrace};
+pub use cpu::{Cpu, construct_cpu};
+pub use instruction::{EffectiveAddress, Instruction, InstructionPrefixes, Mnemonic, Operand, OperandWidth, RepeatPrefix};
+pub use registers::{Register, SegmentRegister, Value, ValueEnum};
+pub use io_bus::{IoAccess, IoBus, LoggingIoBus, construct_io_bus};
@@ -39,6 +39,19 @@ pub struct decimal_memory_contents {
     pub modified_value: Value,
 }
 
+// The 8086 has 20 address lines so it can address 1 MiB of memory.
+pub const MEMORY_SIZE: usize = 1024 * 1024;
+
+pub fn construct_memory() -> Vec<memory_struct> {
+    return vec![memory_struct { address_contents: memory_contents { modified_bits: bits_struct{bits: 0, initialized: false}, original_bits: bits_struct{bits: 0, initialized: false}} }; MEMORY_SIZE];
+}
+
+// The physical address is segment * 16 + offset. The offset is 16 bits so it wraps around inside of the segment
+// and the addresses past the 1 MiB boundary wrap around to the start of the memory like they do on the real chip.
+pub fn get_physical_address(segment: usize, offset: usize) -> usize {
+    return ((segment << 4) + (offset & 0xFFFF)) % MEMORY_SIZE
+}
+
 // Some instructions have a displacement which means the memory address is actually the memory address + displacement. We're handling it in this function.
 fn adjust_memory_address(memory_mode: MemoryModeEnum, memory_address: usize, displacement: usize) -> usize {
     match memory_mode {
@@ -63,15 +76,15 @@ fn modified_16_bit_memory_location_is_initialized(first_byte: memory_struct, sec
     return second_byte.address_contents.modified_bits.initialized && first_byte.address_contents.modified_bits.initialized;
 }
 
-pub fn load_memory_contents_as_decimal_and_optionally_update_original_value(memory: &mut [memory_struct], memory_mode: MemoryModeEnum, memory_address: usize, displacement: usize, is_word_size: bool, update_original_value: bool) -> decimal_memory_contents {
-    assert!(memory_address < memory.len(), "Address was larger than than the available memory.");
-
-    let m_memory_address = adjust_memory_address(memory_mode, memory_address, displacement);
+pub fn load_memory_contents_as_decimal_and_optionally_update_original_value(memory: &mut [memory_struct], memory_mode: MemoryModeEnum, segment: usize, memory_address: usize, displacement: usize, is_word_size: bool, update_original_value: bool) -> decimal_memory_contents {
+    let offset = adjust_memory_address(memory_mode, memory_address, displacement);
+    let m_memory_address = get_physical_address(segment, offset);
+    let m_next_memory_address = get_physical_address(segment, offset + 1);
 
     if instruction_uses_memory(memory_mode) {
         if is_word_size {
             let first_byte = memory[m_memory_address];
-            let second_byte = memory[m_memory_address + 1];
+            let second_byte = memory[m_next_memory_address];
 
             if !original_16_bit_memory_location_is_initialized(first_byte, second_byte) && !modified_16_bit_memory_location_is_initialized(first_byte, second_byte) {
                 return decimal_memory_contents{
@@ -91,7 +104,7 @@ pub fn load_memory_contents_as_decimal_and_optionally_update_original_value(memo
                 };
                 if update_original_value { // This is true only when the destination is a memory location.
                     memory[m_memory_address].address_contents.original_bits = memory[m_memory_address].address_contents.modified_bits;
-                    memory[m_memory_address + 1].address_contents.original_bits = memory[m_memory_address + 1].address_contents.modified_bits;
+                    memory[m_next_memory_address].address_contents.original_bits = memory[m_next_memory_address].address_contents.modified_bits;
                 }
 
                 return decimal_memory_contents;
@@ -112,7 +125,7 @@ pub fn load_memory_contents_as_decimal_and_optionally_update_original_value(memo
 
             if update_original_value { // This is true only when the destination is a memory location.
                 memory[m_memory_address].address_contents.original_bits = memory[m_memory_address].address_contents.modified_bits;
-                memory[m_memory_address + 1].address_contents.original_bits = memory[m_memory_address + 1].address_contents.modified_bits;
+                memory[m_next_memory_address].address_contents.original_bits = memory[m_next_memory_address].address_contents.modified_bits;
             }
 
             return decimal_memory_contents;
@@ -161,46 +174,47 @@ pub fn load_memory_contents_as_decimal_and_optionally_update_original_value(memo
     }
 }
 
-pub fn store_memory_value(memory: &mut [memory_struct], memory_address: usize, displacement: usize, value: Value, mnemonic: &'static str, is_word_size: bool) -> () {
-    let mut updated_memory_address = memory_address;
-    updated_memory_address += displacement;
-
-    if mnemonic != "cmp" {
-        let mut updated_value: Value = Value{value: ValueEnum::Uninitialized, is_signed: false };
-        if mnemonic == "mov" {
-            updated_value = value;
-        } else if mnemonic == "add" || mnemonic == "sub" {
-            let memory_contents: Value;
-
-            if is_word_size {
-                let combined = combine_bytes(memory[memory_address + 1].address_contents.original_bits.bits, memory[memory_address].address_contents.original_bits.bits);
-                let val = ValueEnum::WordSize(combined);
-                memory_contents = Value { value: val, is_signed: number_is_signed(val) };
-            } else {
-                let val = ValueEnum::ByteSize(memory[memory_address].address_contents.original_bits.bits);
-                memory_contents = Value { value: val, is_signed: number_is_signed(val) };
-            }
-
-            if mnemonic == "add" {
-                updated_value = memory_contents.wrap_add_and_return_result(value.value);
-            } else {
-                updated_value = memory_contents.wrap_sub_and_return_result(value.value);
-            }
-        }
-        if let ValueEnum::WordSize(val) = updated_value.value {
-                let memory_contents = separate_word_sized_value_into_bytes(usize::try_from(val).unwrap());
+// Writes the value into the modified bits of the memory location, word sized values are stored in little endian.
+pub fn write_memory_value(memory: &mut [memory_struct], segment: usize, offset: usize, value: ValueEnum) -> () {
+    let memory_address = get_physical_address(segment, offset);
+    let next_memory_address = get_physical_address(segment, offset + 1);
+    if let ValueEnum::WordSize(val) = value {
+        let memory_contents = separate_word_sized_value_into_bytes(usize::from(val));
+
+        memory[memory_address].address_contents.modified_bits.bits = memory_contents.lower_byte;
+        memory[memory_address].address_contents.modified_bits.initialized = true;
+
+        memory[next_memory_address].address_contents.modified_bits.bits = memory_contents.upper_byte;
+        memory[next_memory_address].address_contents.modified_bits.initialized = true;
+    } else if let ValueEnum::ByteSize(val) = value {
+        memory[memory_address].address_contents.modified_bits.bits = val;
+        memory[memory_address].address_contents.modified_bits.initialized = true;
+    } else {
+        panic!("we should not get here ever");
+    }
+}
 
-                memory[updated_memory_address].address_contents.modified_bits.bits = memory_contents.lower_byte;
-                memory[updated_memory_address].address_contents.modified_bits.initialized = true;
+// Reads the modified bits of the memory location, the value is uninitialized if any of its bytes has not been written to yet.
+pub fn read_memory_value(memory: &[memory_struct], segment: usize, offset: usize, is_word_size: bool) -> ValueEnum {
+    let first_byte = memory[get_physical_address(segment, offset)].address_contents.modified_bits;
+    if !is_word_size {
+        return if first_byte.initialized { ValueEnum::ByteSize(first_byte.bits) } else { ValueEnum::Uninitialized }
+    }
+    let second_byte = memory[get_physical_address(segment, offset + 1)].address_contents.modified_bits;
+    if first_byte.initialized && second_byte.initialized {
+        return ValueEnum::WordSize(combine_bytes(second_byte.bits, first_byte.bits))
+    }
+    return ValueEnum::Uninitialized
+}
 
-                memory[updated_memory_address + 1].address_contents.modified_bits.bits = memory_contents.upper_byte;
-                memory[updated_memory_address + 1].address_contents.modified_bits.initialized = true;
-        } else if let ValueEnum::ByteSize(val) = updated_value.value {
-            memory[updated_memory_address].address_contents.modified_bits.bits = val;
-            memory[updated_memory_address].address_contents.modified_bits.initialized = true;
-        } else {
-            panic!("we should not get here ever");
-        }
+// Writes the value into both the original and the modified bits. This is used to set up the memory before the simulation
+// so the value does not show up as a change in the trace of the first instruction that touches it.
+pub fn initialize_memory_value(memory: &mut [memory_struct], segment: usize, offset: usize, value: ValueEnum) -> () {
+    write_memory_value(memory, segment, offset, value);
+    let size = if let ValueEnum::WordSize(_) = value { 2 } else { 1 };
+    for i in 0..size {
+        let memory_address = get_physical_address(segment, offset + i);
+        memory[memory_address].address_contents.original_bits = memory[memory_address].address_contents.modified_bits;
     }
 }
 
@@ -221,7 +235,7 @@ pub fn separate_word_sized_value_into_bytes(value: usize) -> word_sized_value_by
     };
 }
 
-pub fn get_displacement(binary_contents: &Vec<u8>, i: usize, memory_mode: MemoryModeEnum) -> usize {
+pub fn get_displacement(binary_contents: &[u8], i: usize, memory_mode: MemoryModeEnum) -> usize {
     if memory_mode == MemoryModeNoDisplacement {
         return 0;
     } else if memory_mode == MemoryMode8Bit {
@@ -233,14 +247,14 @@ pub fn get_displacement(binary_contents: &Vec<u8>, i: usize, memory_mode: Memory
     }
 }
 
-fn get_16_bit_displacement(binary_contents: &Vec<u8>, i: usize) -> usize {
+fn get_16_bit_displacement(binary_contents: &[u8], i: usize) -> usize {
     let first_disp = binary_contents[i + 2];
     let second_disp = binary_contents[i + 3];
     let displacement = combine_bytes(second_disp, first_disp);
     displacement as usize
 }
 
-fn get_8_bit_displacement(binary_contents: &Vec<u8>, i: usize) -> usize {
+fn get_8_bit_displacement(binary_contents: &[u8], i: usize) -> usize {
     let first_disp = binary_contents[i + 2];
     return first_disp as usize
 }
@@ -1,18 +1,24 @@
-use std::ops::Sub;
-use crate::bits::{InstructionType, MemoryModeEnum};
-use crate::bits::InstructionType::*;
+use std::fmt;
 use crate::flag_registers::{number_is_signed};
+use crate::instruction::Mnemonic;
 
+/// The contents of a register or a memory location.
 #[derive(Copy, Clone,Debug)]
 pub enum ValueEnum {
+    /// An 8 bit value.
     ByteSize(u8),
+    /// A 16 bit value.
     WordSize(u16),
+    /// Nothing has been written there yet, reads as zero.
     Uninitialized,
 }
 
+/// A value together with whether it should be printed as a signed number.
 #[derive(Copy, Clone, Debug)]
 pub struct Value {
+    /// The raw bits.
     pub value: ValueEnum,
+    /// Whether the top bit of the value is set.
     pub is_signed: bool,
 }
 
@@ -27,31 +33,32 @@ impl PartialEq for ValueEnum {
     }
 }
 
-impl Sub for ValueEnum {
-    type Output = Self;
-
-    fn sub(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (ValueEnum::ByteSize(a), ValueEnum::ByteSize(b)) => ValueEnum::ByteSize(a - b),
-            (ValueEnum::WordSize(a), ValueEnum::WordSize(b)) => ValueEnum::WordSize(a - b),
-            _ => panic!("Cannot subtract {:?} from {:?}", rhs, self),
+impl ValueEnum {
+    // Performs the and, or, xor and test operations, test is just an and that does not store the result.
+    // Uninitialized values are handled as zero because that's what the simulated registers and memory contain before they're written to.
+    pub(crate) fn logical_operation(self, mnemonic: Mnemonic, value_src: ValueEnum, is_word_size: bool) -> ValueEnum {
+        let dest = self.get_usize();
+        let src = value_src.get_usize();
+        let result = match mnemonic {
+            Mnemonic::And | Mnemonic::Test => dest & src,
+            Mnemonic::Or => dest | src,
+            Mnemonic::Xor => dest ^ src,
+            _ => panic!("{} is not a logical operation", mnemonic),
+        };
+        if is_word_size {
+            return ValueEnum::WordSize(result as u16)
+        } else {
+            return ValueEnum::ByteSize(result as u8)
         }
     }
 }
-
-// Implement a wrapping subtraction for ValueEnum.
 impl ValueEnum {
-    pub fn wrap_sub(&self, value_src: ValueEnum) -> ValueEnum {
-        match self {
-            ValueEnum::ByteSize(val) => {
-                let result_after_wrap = ValueEnum::ByteSize(val.wrapping_sub(value_src.get_usize() as u8));
-                return result_after_wrap;
-            },
-            ValueEnum::WordSize(val) => {
-                let result_after_wrap = ValueEnum::WordSize(val.wrapping_sub(value_src.get_usize() as u16));
-                return result_after_wrap;
-            },
-            ValueEnum::Uninitialized => panic!("Cannot subtract {:?} from {:?}", value_src, self),
+    // Creates a byte or word sized value, the bits that don't fit are thrown away.
+    pub(crate) fn from_usize(value: usize, is_word_size: bool) -> ValueEnum {
+        if is_word_size {
+            return ValueEnum::WordSize(value as u16)
+        } else {
+            return ValueEnum::ByteSize(value as u8)
         }
     }
 }
@@ -61,6 +68,7 @@ impl PartialEq for Value {
     }
 }
 impl ValueEnum {
+    /// Returns the raw bits, zero when uninitialized.
     pub fn get_usize(self) -> usize {
         match self {
             Self::ByteSize(val) => val as usize,
@@ -70,84 +78,78 @@ impl ValueEnum {
         }
     }
 }
+// The carry (or the borrow with subtraction) out of the highest bit and the signed overflow of an addition or a subtraction.
+// These are needed to set CF and OF and to chain adc and sbb for numbers larger than a word.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct carry_and_overflow {
+    pub carry: bool,
+    pub overflow: bool,
+}
+
 impl Value {
-    pub fn wrap_add(&mut self, value_src: ValueEnum) {
+    // Adds the source and the carry_in (adc uses it, add does not) to the value.
+    pub(crate) fn wrap_add(&mut self, value_src: ValueEnum, carry_in: bool) -> carry_and_overflow {
         let value_src_to_usize = value_src.get_usize();  // we can actually do this because the source type does not matter if it
                                              // does not change the underlying value.
         match self.value {
             ValueEnum::ByteSize(val) => {
-                let result_after_wrap = ValueEnum::ByteSize(val.wrapping_add(u8::try_from(value_src_to_usize).expect("we were sure that the value would fit in u8 but it didn't.")));
-                let val = Value{value: result_after_wrap, is_signed: number_is_signed(result_after_wrap)};
-                *self = val
+                let src = u8::try_from(value_src_to_usize).expect("we were sure that the value would fit in u8 but it didn't.");
+                let full_result = val as usize + src as usize + carry_in as usize;
+                let result = full_result as u8;
+                let result_after_wrap = ValueEnum::ByteSize(result);
+                *self = Value{value: result_after_wrap, is_signed: number_is_signed(result_after_wrap)};
+                // Adding two numbers with the same sign can't result in a number with a different sign.
+                return carry_and_overflow{carry: full_result > 0xFF, overflow: (val ^ result) & (src ^ result) & 0x80 != 0}
             },
             ValueEnum::WordSize(val) => {
-                let result_after_wrap = ValueEnum::WordSize(val.wrapping_add(u16::try_from(value_src_to_usize).expect("we were sure that the value would fit in u16 but it didn't.")));
-                let val = Value{value: result_after_wrap, is_signed: number_is_signed(result_after_wrap)};
-                *self = val;
+                let src = u16::try_from(value_src_to_usize).expect("we were sure that the value would fit in u16 but it didn't.");
+                let full_result = val as usize + src as usize + carry_in as usize;
+                let result = full_result as u16;
+                let result_after_wrap = ValueEnum::WordSize(result);
+                *self = Value{value: result_after_wrap, is_signed: number_is_signed(result_after_wrap)};
+                return carry_and_overflow{carry: full_result > 0xFFFF, overflow: (val ^ result) & (src ^ result) & 0x8000 != 0}
             },
-            ValueEnum::Uninitialized => (),
+            ValueEnum::Uninitialized => carry_and_overflow{carry: false, overflow: false},
         }
     }
 
-    pub fn wrap_sub(&mut self, value_src: ValueEnum) {
+    // Subtracts the source and the borrow_in (sbb uses it, sub and cmp don't) from the value.
+    pub(crate) fn wrap_sub(&mut self, value_src: ValueEnum, borrow_in: bool) -> carry_and_overflow {
         let value_src_to_usize = value_src.get_usize(); // we can actually do this because the source type does not matter if it
                                                               // does not change the underlying value.
         match self.value {
             ValueEnum::ByteSize(val) => {
-                let result_after_wrap = ValueEnum::ByteSize(val.wrapping_sub(u8::try_from(value_src_to_usize).expect("we were sure that the value would fit in u8 but it didn't.")));
-                let val = Value{value: result_after_wrap, is_signed: number_is_signed(result_after_wrap)};
-                *self = val
-            },
-            ValueEnum::WordSize(val) => {
-                let result_after_wrap = ValueEnum::WordSize(val.wrapping_sub(u16::try_from(value_src_to_usize).expect("we were sure that the value would fit in u16 but it didn't.")));
-                let val = Value{value: result_after_wrap, is_signed: number_is_signed(result_after_wrap)};
-                *self = val;
-            },
-            ValueEnum::Uninitialized => (),
-        }
-    }
-
-    pub fn wrap_add_and_return_result(self, value_src: ValueEnum) -> Value {
-        let self_value_to_usize = value_src.get_usize(); // we can actually do this because the source type does not matter if it
-                                             // does not change the underlying value.
-        match self.value {
-            ValueEnum::ByteSize(val) => {
-                let result_after_wrap = ValueEnum::ByteSize(val.wrapping_add(u8::try_from(self_value_to_usize).expect("we were sure that the value would fit in u8 but it didn't.")));
-                let val = Value{value: result_after_wrap, is_signed: number_is_signed(result_after_wrap)};
-                return val;
-            },
-            ValueEnum::WordSize(val) => {
-                let result_after_wrap = ValueEnum::WordSize(val.wrapping_add(u16::try_from(self_value_to_usize).expect("we were sure that the value would fit in u16 but it didn't.")));
-                let val = Value{value: result_after_wrap, is_signed: number_is_signed(result_after_wrap)};
-                return val;
-            },
-            ValueEnum::Uninitialized => self,
-        }
-    }
-    pub fn wrap_sub_and_return_result(self, value_src: ValueEnum) -> Value {
-        let self_value_to_usize = value_src.get_usize(); // we can actually do this because the source type does not matter if it
-                                                          //
-        match self.value {
-            ValueEnum::ByteSize(val) => {
-                let result_after_wrap = ValueEnum::ByteSize(val.wrapping_sub(u8::try_from(self_value_to_usize).expect("we were sure that the value would fit in u8 but it didn't.")));
-                let val = Value{value: result_after_wrap, is_signed: number_is_signed(result_after_wrap)};
-                return val;
+                let src = u8::try_from(value_src_to_usize).expect("we were sure that the value would fit in u8 but it didn't.");
+                let full_result = val as isize - src as isize - borrow_in as isize;
+                let result = full_result as u8;
+                let result_after_wrap = ValueEnum::ByteSize(result);
+                *self = Value{value: result_after_wrap, is_signed: number_is_signed(result_after_wrap)};
+                // Subtracting a number with a different sign should move the result away from zero, if the sign flipped it overflowed.
+                return carry_and_overflow{carry: full_result < 0, overflow: (val ^ src) & (val ^ result) & 0x80 != 0}
             },
             ValueEnum::WordSize(val) => {
-                let result_after_wrap = ValueEnum::WordSize(val.wrapping_sub(u16::try_from(self_value_to_usize).expect("we were sure that the value would fit in u16 but it didn't.")));
-                let val = Value{value: result_after_wrap, is_signed: number_is_signed(result_after_wrap)};
-                return val;
+                let src = u16::try_from(value_src_to_usize).expect("we were sure that the value would fit in u16 but it didn't.");
+                let full_result = val as isize - src as isize - borrow_in as isize;
+                let result = full_result as u16;
+                let result_after_wrap = ValueEnum::WordSize(result);
+                *self = Value{value: result_after_wrap, is_signed: number_is_signed(result_after_wrap)};
+                return carry_and_overflow{carry: full_result < 0, overflow: (val ^ src) & (val ^ result) & 0x8000 != 0}
             },
-            ValueEnum::Uninitialized => self,
+            ValueEnum::Uninitialized => carry_and_overflow{carry: false, overflow: false},
         }
     }
 
+    /// Formats the value as a decimal number, signed if is_signed is set.
     pub fn get_string_number_from_bits(self) -> String {
         if self.is_signed {
             match self.value {
                 ValueEnum::ByteSize(val) => {
                     let twos_complement_number = self.twos_complement_8_bit(val);
-                    return format!("{}{}", "-", twos_complement_number.to_string());
+                    if twos_complement_number < 0 { // -128 does not fit into a positive i8 so it stays negative.
+                        return format!("{}", twos_complement_number.to_string());
+                    } else {
+                        return format!("{}{}", "-", twos_complement_number.to_string());
+                    }
                 },
                 ValueEnum::WordSize(val) => {
                     let twos_complement_number = self.twos_complement_16_bit(val);
@@ -173,20 +175,121 @@ impl Value {
 
 }
 
+/// The registers the instructions can name. The byte registers are the low and high halves of ax, cx, dx and bx.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Register {
+    /// The accumulator.
+    AX,
+    /// The count register, loop and the repeat prefixes use it.
+    CX,
+    /// The data register, the upper half of the wide multiply and divide results.
+    DX,
+    /// The base register.
+    BX,
+    /// The stack pointer.
+    SP,
+    /// The base pointer, memory operands based on it use ss.
+    BP,
+    /// The source index.
+    SI,
+    /// The destination index.
+    DI,
+    /// The low byte of ax.
+    AL,
+    /// The low byte of cx, the shifts and rotates use it as the count.
+    CL,
+    /// The low byte of dx.
+    DL,
+    /// The low byte of bx.
+    BL,
+    /// The high byte of ax.
+    AH,
+    /// The high byte of cx.
+    CH,
+    /// The high byte of dx.
+    DH,
+    /// The high byte of bx.
+    BH,
+    /// The extra segment.
+    ES,
+    /// The code segment.
+    CS,
+    /// The stack segment.
+    SS,
+    /// The data segment.
+    DS,
+}
+
+impl Register {
+    /// The name of the register like it's written in the assembly.
+    pub fn name(self) -> &'static str {
+        return match self {
+            Register::AX => "ax", Register::CX => "cx", Register::DX => "dx", Register::BX => "bx",
+            Register::SP => "sp", Register::BP => "bp", Register::SI => "si", Register::DI => "di",
+            Register::AL => "al", Register::CL => "cl", Register::DL => "dl", Register::BL => "bl",
+            Register::AH => "ah", Register::CH => "ch", Register::DH => "dh", Register::BH => "bh",
+            Register::ES => "es", Register::CS => "cs", Register::SS => "ss", Register::DS => "ds",
+        }
+    }
+
+    /// false for the byte registers, everything else is 16 bits.
+    pub fn is_word_size(self) -> bool {
+        return !matches!(self, Register::AL | Register::CL | Register::DL | Register::BL | Register::AH | Register::CH | Register::DH | Register::BH)
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.name())
+    }
+}
+
+/// The segment registers, a memory operand is always relative to one of these.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SegmentRegister {
+    /// The extra segment.
+    ES,
+    /// The code segment.
+    CS,
+    /// The stack segment.
+    SS,
+    /// The data segment.
+    DS,
+}
+
+impl From<SegmentRegister> for Register {
+    fn from(segment_register: SegmentRegister) -> Register {
+        return match segment_register {
+            SegmentRegister::ES => Register::ES,
+            SegmentRegister::CS => Register::CS,
+            SegmentRegister::SS => Register::SS,
+            SegmentRegister::DS => Register::DS,
+        }
+    }
+}
+
+impl fmt::Display for SegmentRegister {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", Register::from(*self).name())
+    }
+}
+
+// The simulated value of a register, the original value is what it was before the current instruction.
 #[derive(Copy, Clone)]
-pub struct Register {
-   pub register:       &'static str,
+pub struct RegisterState {
+   pub register:       Register,
    pub updated_value:  Value,
    pub original_value: Value,
 }
 
-const REGISTERS: [&str; 16] = [
-    "ax", "cx", "dx", "bx", "sp", "bp", "si", "di",
-    "al", "cl", "dl", "bl", "ah", "ch", "dh", "bh",
+const REGISTERS: [Register; 20] = [
+    Register::AX, Register::CX, Register::DX, Register::BX, Register::SP, Register::BP, Register::SI, Register::DI,
+    Register::AL, Register::CL, Register::DL, Register::BL, Register::AH, Register::CH, Register::DH, Register::BH,
+    Register::ES, Register::CS, Register::SS, Register::DS,
 ];
 
-pub fn construct_registers() -> Vec<Register>{
-    REGISTERS.iter().map(|&register| Register {
+pub fn construct_registers() -> Vec<RegisterState>{
+    REGISTERS.iter().map(|&register| RegisterState {
         register,
         updated_value: Value{value: ValueEnum::Uninitialized, is_signed: false},
         original_value: Value{value: ValueEnum::Uninitialized, is_signed: false},
@@ -194,12 +297,7 @@ pub fn construct_registers() -> Vec<Register>{
 }
 
 
-pub fn register_contains_multiple_registers(register: &str) -> bool {
-    return register.contains("+") || register.contains("-")
-}
-
-pub fn get_register_state(register: &str, registers: &Vec<Register>) -> Register {
-    assert!(!register_contains_multiple_registers(register), "Register contained multiple registers, it should be handled in the caller.");
+pub fn get_register_state(register: Register, registers: &Vec<RegisterState>) -> RegisterState {
     for reg in registers.iter() {
         if reg.register == register {
             return reg.clone()
@@ -208,62 +306,66 @@ pub fn get_register_state(register: &str, registers: &Vec<Register>) -> Register
     panic!("Register not found, this should never happen. Register that was not found was {}", register);
 }
 
-pub fn update_register_value(register_to_update: &str, value: ValueEnum, registers: &mut Vec<Register>, instruction: InstructionType, memory_mode: MemoryModeEnum, mnemonic: &'static str, is_word_size: bool) -> () {
+// The byte registers are the low and high halves of ax, cx, dx and bx so writing to one of them also changes the other.
+// Returns the word register and its low and high halves if the register overlaps with others.
+fn get_overlapping_registers(register: Register) -> Option<(Register, Register, Register)> {
+    match register {
+        Register::AX | Register::AL | Register::AH => Some((Register::AX, Register::AL, Register::AH)),
+        Register::CX | Register::CL | Register::CH => Some((Register::CX, Register::CL, Register::CH)),
+        Register::DX | Register::DL | Register::DH => Some((Register::DX, Register::DL, Register::DH)),
+        Register::BX | Register::BL | Register::BH => Some((Register::BX, Register::BL, Register::BH)),
+        _ => None,
+    }
+}
+
+// Copies the value of the register that was just written into the registers that overlap with it.
+// The half that has not been written to yet is handled as 0 when the word register is put together.
+fn sync_overlapping_registers(register_written: Register, registers: &mut Vec<RegisterState>, sync_original_value: bool) -> () {
+    let Some((word, low, high)) = get_overlapping_registers(register_written) else { return };
+    let value_of = |register: Register, registers: &Vec<RegisterState>| {
+        let state = get_register_state(register, registers);
+        if sync_original_value { state.original_value.value } else { state.updated_value.value }
+    };
+    let written = value_of(register_written, registers);
+    if let ValueEnum::Uninitialized = written { return }
+
+    let mut new_values: Vec<(Register, ValueEnum)> = Vec::new();
+    if register_written == word {
+        new_values.push((low, ValueEnum::ByteSize((written.get_usize() & 0xFF) as u8)));
+        new_values.push((high, ValueEnum::ByteSize((written.get_usize() >> 8) as u8)));
+    } else {
+        let low_value = value_of(low, registers).get_usize();
+        let high_value = value_of(high, registers).get_usize();
+        new_values.push((word, ValueEnum::WordSize(((high_value << 8) | low_value) as u16)));
+    }
     for register in registers.iter_mut() {
-        if register.register == register_to_update {
-            match instruction {
-                ImmediateToAccumulatorADD => {
-                    register.updated_value.wrap_add(value);
-                },
-                ImmediateToAccumulatorSUB => {
-                    register.updated_value.wrap_sub(value);
-                }
-                ImmediateToRegisterMemory | RegisterMemory => {
-                    match memory_mode {
-                        MemoryModeEnum::RegisterMode | MemoryModeEnum::MemoryModeNoDisplacement | MemoryModeEnum::MemoryMode8Bit | MemoryModeEnum::MemoryMode16Bit | MemoryModeEnum::DirectMemoryOperation => {
-                            match mnemonic {
-                                "mov" => {
-                                    if let ValueEnum::Uninitialized = value {
-                                        // We do the stuff in this branch because without it, if we initialize a register with 0 it's handled as "uninitialized" because the register
-                                        // has not been tinkered with yet. That is not however uninitialized, it's just initializing a register with 0.
-                                        if is_word_size {
-                                            register.updated_value = Value { value: ValueEnum::WordSize(0), is_signed: number_is_signed(value) }
-                                        } else {
-                                            register.updated_value = Value { value: ValueEnum::ByteSize(0), is_signed: number_is_signed(value) }
-                                        }
-                                    } else {
-                                        register.updated_value = Value { value, is_signed: number_is_signed(value) }
-                                    }
-                                },
-                                "add" => register.updated_value.wrap_add(value),
-                                "sub" => register.updated_value.wrap_sub(value),
-                                "cmp" => (),
-                                _ => panic!("Unknown mnemonic {}", mnemonic),
-                            }
-                        }
-                    }
-                    return
-                },
-                ImmediateToRegisterMOV => register.updated_value = Value{value, is_signed: number_is_signed(value)},
-                _ => () // Conditional jumps, CMP instructions.
+        for (name, value) in new_values.iter() {
+            if register.register == *name {
+                let value = Value { value: *value, is_signed: number_is_signed(*value) };
+                if sync_original_value { register.original_value = value } else { register.updated_value = value }
             }
+        }
+    }
+}
+
+// Overwrites the current value of the register, this is used when the caller has already calculated the result.
+pub fn set_register_value(register_to_update: Register, value: ValueEnum, registers: &mut Vec<RegisterState>) -> () {
+    for register in registers.iter_mut() {
+        if register.register == register_to_update {
+            register.updated_value = Value { value, is_signed: number_is_signed(value) };
+            sync_overlapping_registers(register_to_update, registers, false);
             return
         }
     }
     panic!("Register not found, this should never happen. Register that was not found was {}", register_to_update);
 }
 
-pub fn update_original_register_value(register_to_update: &'static str, value: ValueEnum, registers: &mut Vec<Register>) -> () {
+pub fn update_original_register_value(register_to_update: Register, value: ValueEnum, registers: &mut Vec<RegisterState>) -> () {
     if let ValueEnum::Uninitialized = value {return}
     for reg in registers.iter_mut() {
         if reg.register == register_to_update {
             reg.original_value = Value { value, is_signed: number_is_signed(value) };
         }
     }
+    sync_overlapping_registers(register_to_update, registers, true);
 }
-
-pub fn print_out_state_of_all_registers(registers: Vec<Register>) {
-    for register in registers {
-        println!("\t{}: {}", register.register, register.updated_value.get_string_number_from_bits());
-    }
-}
\ No newline at end of file